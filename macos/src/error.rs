@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::format::MacClipboardFormat;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+	#[error("Failed to get the general pasteboard")]
+	NoPasteboard,
+	#[error("The pasteboard refused to take {format:?} data")]
+	Write { format: MacClipboardFormat },
+	#[error("Failed to convert between a string and an NSString")]
+	StringConversion,
+	#[error("Failed to decode string as UTF-8: {0}")]
+	InvalidString(std::str::Utf8Error),
+	#[error("File path #{idx} can't be represented as a file URL")]
+	FilePath { idx: usize },
+	#[error("{0:?} is not a valid file URL")]
+	FileUrl(String),
+	#[error("A valid image could not be constructed from the clipboard data")]
+	InvalidImage,
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::{
+	convert::Infallible,
+	fmt::{self, Display},
+	str::FromStr,
+};
+
+/// A pasteboard type, identified by its uniform type identifier.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MacClipboardFormat {
+	/// `public.utf8-plain-text`, also known as `NSPasteboardTypeString`.
+	String,
+	/// `public.file-url`, also known as `NSPasteboardTypeFileURL`.
+	FileUrl,
+	/// `public.url`, also known as `NSPasteboardTypeURL`.
+	Url,
+	/// `public.png`, also known as `NSPasteboardTypePNG`.
+	Png,
+	/// `public.tiff`, also known as `NSPasteboardTypeTIFF`.
+	Tiff,
+	/// `public.html`, also known as `NSPasteboardTypeHTML`.
+	Html,
+	/// `public.rtf`, also known as `NSPasteboardTypeRTF`.
+	Rtf,
+	/// Any other type, by its UTI.
+	Other(String),
+}
+
+impl MacClipboardFormat {
+	/// Gets the format for a uniform type identifier.
+	pub fn from_uti(uti: &str) -> Self {
+		match uti {
+			"public.utf8-plain-text" => Self::String,
+			"public.file-url" => Self::FileUrl,
+			"public.url" => Self::Url,
+			"public.png" => Self::Png,
+			"public.tiff" => Self::Tiff,
+			"public.html" => Self::Html,
+			"public.rtf" => Self::Rtf,
+			other => Self::Other(other.to_owned()),
+		}
+	}
+
+	/// Returns the uniform type identifier of this format.
+	pub fn uti(&self) -> &str {
+		match self {
+			Self::String => "public.utf8-plain-text",
+			Self::FileUrl => "public.file-url",
+			Self::Url => "public.url",
+			Self::Png => "public.png",
+			Self::Tiff => "public.tiff",
+			Self::Html => "public.html",
+			Self::Rtf => "public.rtf",
+			Self::Other(uti) => uti,
+		}
+	}
+}
+
+impl Display for MacClipboardFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.uti())
+	}
+}
+
+impl FromStr for MacClipboardFormat {
+	type Err = Infallible;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Ok(Self::from_uti(name))
+	}
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Small helpers for moving data between Rust and Foundation types.
+
+use crate::error::{Error, Result};
+use objc::{class, msg_send, runtime::Object, sel, sel_impl};
+use std::{
+	ffi::CStr,
+	os::raw::{c_char, c_void},
+};
+
+pub(crate) type Id = *mut Object;
+
+const NS_UTF8_STRING_ENCODING: usize = 4;
+
+/// Creates an autoreleased `NSString` from UTF-8 bytes, or returns nil if they aren't valid UTF-8.
+pub(crate) unsafe fn ns_string_from_bytes(bytes: &[u8]) -> Id {
+	let string: Id = msg_send![class!(NSString), alloc];
+	let string: Id = msg_send![
+		string,
+		initWithBytes: bytes.as_ptr() as *const c_void
+		length: bytes.len()
+		encoding: NS_UTF8_STRING_ENCODING
+	];
+	if string.is_null() {
+		return string;
+	}
+	msg_send![string, autorelease]
+}
+
+/// Creates an autoreleased `NSString` from a Rust string.
+pub(crate) unsafe fn ns_string(string: &str) -> Result<Id> {
+	let string = ns_string_from_bytes(string.as_bytes());
+	if string.is_null() {
+		return Err(Error::StringConversion);
+	}
+	Ok(string)
+}
+
+/// Copies an `NSString` into a Rust string.
+pub(crate) unsafe fn string_from_ns(string: Id) -> Result<String> {
+	let utf8: *const c_char = msg_send![string, UTF8String];
+	if utf8.is_null() {
+		return Err(Error::StringConversion);
+	}
+	CStr::from_ptr(utf8)
+		.to_str()
+		.map(str::to_owned)
+		.map_err(Error::InvalidString)
+}
+
+/// Creates an autoreleased `NSData` holding a copy of `bytes`.
+pub(crate) unsafe fn ns_data(bytes: &[u8]) -> Id {
+	msg_send![
+		class!(NSData),
+		dataWithBytes: bytes.as_ptr() as *const c_void
+		length: bytes.len()
+	]
+}
+
+/// Copies the contents of an `NSData` into a `Vec`.
+pub(crate) unsafe fn bytes_from_ns_data(data: Id) -> Vec<u8> {
+	let length: usize = msg_send![data, length];
+	if length == 0 {
+		// The bytes pointer may be null for empty data.
+		return Vec::new();
+	}
+	let bytes: *const u8 = msg_send![data, bytes];
+	std::slice::from_raw_parts(bytes, length).to_vec()
+}
+
+/// Creates an autoreleased `NSArray` from a list of objects.
+pub(crate) unsafe fn ns_array(objects: &[Id]) -> Id {
+	msg_send![
+		class!(NSArray),
+		arrayWithObjects: objects.as_ptr()
+		count: objects.len()
+	]
+}
+
+/// Returns the objects in an `NSArray`.
+pub(crate) unsafe fn array_objects(array: Id) -> Vec<Id> {
+	let count: usize = msg_send![array, count];
+	(0..count)
+		.map(|idx| msg_send![array, objectAtIndex: idx])
+		.collect()
+}
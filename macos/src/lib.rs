@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+pub mod error;
+pub mod format;
+pub(crate) mod foundation;
+
+use self::{
+	error::{Error, Result},
+	format::MacClipboardFormat,
+	foundation::Id,
+};
+use objc::{
+	class, msg_send,
+	rc::autoreleasepool,
+	runtime::{BOOL, NO},
+	sel, sel_impl,
+};
+use std::{
+	ffi::{CStr, OsStr},
+	os::{raw::c_char, unix::ffi::OsStrExt},
+	path::{Path, PathBuf},
+};
+
+// NSPasteboard lives in AppKit, which nothing else would link otherwise.
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {}
+
+/// A handle to the general pasteboard, which is what macOS calls the system clipboard.
+///
+/// Unlike on Windows, the pasteboard doesn't need to be opened, so any number of these can exist at once.
+#[derive(Debug, Clone)]
+pub struct ClipboardHandle {
+	pasteboard: Id,
+}
+
+impl ClipboardHandle {
+	pub fn new() -> Result<Self> {
+		let pasteboard: Id = unsafe { msg_send![class!(NSPasteboard), generalPasteboard] };
+		if pasteboard.is_null() {
+			return Err(Error::NoPasteboard);
+		}
+		Ok(Self { pasteboard })
+	}
+
+	pub fn set_string<StringType: ToString>(&self, string: StringType) -> Result<()> {
+		let string = string.to_string();
+		autoreleasepool(|| unsafe {
+			let string = foundation::ns_string(&string)?;
+			let format = MacClipboardFormat::String;
+			let uti = foundation::ns_string(format.uti())?;
+			self.clear_contents();
+			let written: BOOL = msg_send![self.pasteboard, setString: string forType: uti];
+			if written == NO {
+				return Err(Error::Write { format });
+			}
+			Ok(())
+		})
+	}
+
+	pub fn string(&self) -> Result<Option<String>> {
+		autoreleasepool(|| unsafe {
+			let uti = foundation::ns_string(MacClipboardFormat::String.uti())?;
+			let string: Id = msg_send![self.pasteboard, stringForType: uti];
+			if string.is_null() {
+				return Ok(None);
+			}
+			foundation::string_from_ns(string).map(Some)
+		})
+	}
+
+	/// Gets the list of files on the pasteboard, converted from `file://` URLs to paths.
+	pub fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		autoreleasepool(|| unsafe {
+			let uti = foundation::ns_string(MacClipboardFormat::FileUrl.uti())?;
+			let items: Id = msg_send![self.pasteboard, pasteboardItems];
+			if items.is_null() {
+				return Ok(None);
+			}
+			let mut paths = Vec::new();
+			// Each file is its own pasteboard item.
+			for item in foundation::array_objects(items) {
+				let url_string: Id = msg_send![item, stringForType: uti];
+				if url_string.is_null() {
+					continue;
+				}
+				paths.push(path_from_file_url(url_string)?);
+			}
+			if paths.is_empty() {
+				return Ok(None);
+			}
+			Ok(Some(paths))
+		})
+	}
+
+	pub fn set_files<PathType: AsRef<Path>, PathList: AsRef<[PathType]>>(
+		&self,
+		paths: PathList,
+	) -> Result<()> {
+		autoreleasepool(|| unsafe {
+			let urls = paths
+				.as_ref()
+				.iter()
+				.enumerate()
+				.map(|(idx, path)| {
+					let path =
+						foundation::ns_string_from_bytes(path.as_ref().as_os_str().as_bytes());
+					if path.is_null() {
+						return Err(Error::FilePath { idx });
+					}
+					let url: Id = msg_send![class!(NSURL), fileURLWithPath: path];
+					if url.is_null() {
+						return Err(Error::FilePath { idx });
+					}
+					Ok(url)
+				})
+				.collect::<Result<Vec<Id>>>()?;
+			self.clear_contents();
+			let written: BOOL =
+				msg_send![self.pasteboard, writeObjects: foundation::ns_array(&urls)];
+			if written == NO {
+				return Err(Error::Write {
+					format: MacClipboardFormat::FileUrl,
+				});
+			}
+			Ok(())
+		})
+	}
+
+	/// Gets the image on the pasteboard, preferring PNG data over TIFF data.
+	pub fn image(&self) -> Result<Option<image::RgbImage>> {
+		for (format, image_format) in [
+			(MacClipboardFormat::Png, image::ImageFormat::Png),
+			(MacClipboardFormat::Tiff, image::ImageFormat::Tiff),
+		] {
+			if let Some(data) = self.get_raw(&format)? {
+				let image = image::load_from_memory_with_format(&data, image_format)
+					.map_err(|_| Error::InvalidImage)?;
+				return Ok(Some(image.to_rgb8()));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Gets the raw bytes of a pasteboard type.
+	pub fn get_raw(&self, format: &MacClipboardFormat) -> Result<Option<Vec<u8>>> {
+		autoreleasepool(|| unsafe {
+			let uti = foundation::ns_string(format.uti())?;
+			let data: Id = msg_send![self.pasteboard, dataForType: uti];
+			if data.is_null() {
+				return Ok(None);
+			}
+			Ok(Some(foundation::bytes_from_ns_data(data)))
+		})
+	}
+
+	/// Replaces the contents of the pasteboard with raw bytes, as the given type.
+	pub fn set_raw(&self, format: &MacClipboardFormat, data: &[u8]) -> Result<()> {
+		autoreleasepool(|| unsafe {
+			let uti = foundation::ns_string(format.uti())?;
+			self.clear_contents();
+			let data = foundation::ns_data(data);
+			let written: BOOL = msg_send![self.pasteboard, setData: data forType: uti];
+			if written == NO {
+				return Err(Error::Write {
+					format: format.clone(),
+				});
+			}
+			Ok(())
+		})
+	}
+
+	pub fn empty(&self) -> Result<()> {
+		unsafe { self.clear_contents() };
+		Ok(())
+	}
+
+	pub fn available_formats(&self) -> Result<Vec<MacClipboardFormat>> {
+		autoreleasepool(|| unsafe {
+			let types: Id = msg_send![self.pasteboard, types];
+			if types.is_null() {
+				return Ok(Vec::new());
+			}
+			foundation::array_objects(types)
+				.into_iter()
+				.map(|uti| {
+					foundation::string_from_ns(uti).map(|uti| MacClipboardFormat::from_uti(&uti))
+				})
+				.collect()
+		})
+	}
+
+	/// Clears the pasteboard, which also makes us its owner, as is required before writing to it.
+	unsafe fn clear_contents(&self) {
+		let _: isize = msg_send![self.pasteboard, clearContents];
+	}
+}
+
+/// Converts a `file://` URL string into a path.
+unsafe fn path_from_file_url(url_string: Id) -> Result<PathBuf> {
+	let url: Id = msg_send![class!(NSURL), URLWithString: url_string];
+	let is_file_url: BOOL = if url.is_null() {
+		NO
+	} else {
+		msg_send![url, isFileURL]
+	};
+	if is_file_url == NO {
+		return Err(Error::FileUrl(foundation::string_from_ns(url_string)?));
+	}
+	let path: *const c_char = msg_send![url, fileSystemRepresentation];
+	if path.is_null() {
+		return Err(Error::FileUrl(foundation::string_from_ns(url_string)?));
+	}
+	Ok(PathBuf::from(OsStr::from_bytes(
+		CStr::from_ptr(path).to_bytes(),
+	)))
+}
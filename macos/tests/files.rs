@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(target_os = "macos")]
+
+use clipee_macos::ClipboardHandle;
+use std::path::PathBuf;
+
+#[test]
+pub fn round_trip_files() {
+	let handle = ClipboardHandle::new().expect("failed to get pasteboard");
+	let files_list = vec![
+		PathBuf::from("/Users/Shared"),
+		PathBuf::from("/tmp/clipee test.txt"),
+		PathBuf::from("/tmp/ünïcödé.txt"),
+	];
+	handle
+		.set_files(&files_list)
+		.expect("failed to set files to pasteboard");
+	let files = handle
+		.files()
+		.expect("failed to get files from pasteboard")
+		.expect("files weren't set in pasteboard");
+	assert_eq!(files_list, files, "File list didn't survive the round-trip");
+}
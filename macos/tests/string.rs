@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(target_os = "macos")]
+
+use clipee_macos::{format::MacClipboardFormat, ClipboardHandle};
+
+#[test]
+pub fn round_trip_string() {
+	let text = "héllo, pasteboard 🦀";
+	let handle = ClipboardHandle::new().expect("failed to get pasteboard");
+	handle
+		.set_string(text)
+		.expect("failed to set string to pasteboard");
+	let result = handle
+		.string()
+		.expect("failed to get string from pasteboard")
+		.expect("string wasn't set in pasteboard");
+	assert_eq!(text, result, "String didn't survive round-trip!");
+	let formats = handle
+		.available_formats()
+		.expect("failed to get available formats");
+	assert!(formats.contains(&MacClipboardFormat::String));
+}
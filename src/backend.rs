@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(target_os = "macos")]
+pub mod macos;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub mod wayland;
+#[cfg(windows)]
+pub mod windows;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub mod x11;
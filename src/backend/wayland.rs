@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The Wayland backend isn't the [`SystemClipboard`](crate::SystemClipboard), as it only works on compositors that
+//! implement wlr-data-control, whereas X11 also works through XWayland.
+//! Use [`ClipboardHandle::new`] directly, and fall back to X11 on [`Error::UnsupportedCompositor`].
+
+use crate::Clipboard;
+use clipee_wayland::{
+	error::{Error, Result},
+	ClipboardHandle,
+};
+use image::RgbImage;
+use std::path::PathBuf;
+
+impl Clipboard for ClipboardHandle {
+	type Error = Error;
+	/// A MIME type.
+	type Format = String;
+
+	fn system() -> Result<Self> {
+		ClipboardHandle::new()
+	}
+
+	fn string(&self) -> Result<Option<String>> {
+		ClipboardHandle::string(self)
+	}
+
+	fn set_string(&self, string: &str) -> Result<()> {
+		ClipboardHandle::set_string(self, string)
+	}
+
+	fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		ClipboardHandle::files(self)
+	}
+
+	fn set_files(&self, paths: &[PathBuf]) -> Result<()> {
+		ClipboardHandle::set_files(self, paths)
+	}
+
+	fn image(&self) -> Result<Option<RgbImage>> {
+		ClipboardHandle::image(self)
+	}
+
+	fn get_raw(&self, mime_type: &String) -> Result<Option<Vec<u8>>> {
+		ClipboardHandle::get_raw(self, mime_type)
+	}
+
+	fn available_formats(&self) -> Result<Vec<String>> {
+		ClipboardHandle::available_formats(self)
+	}
+}
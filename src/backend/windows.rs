@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::Clipboard;
+use clipee_windows::{
+	error::{Error, Result},
+	format::ClipboardFormat,
+	ClipboardHandle,
+};
+use image::RgbImage;
+use std::path::PathBuf;
+
+/// The clipboard backend for this platform.
+pub type SystemClipboard = ClipboardHandle;
+
+impl Clipboard for ClipboardHandle {
+	type Error = Error;
+	type Format = ClipboardFormat;
+
+	fn system() -> Result<Self> {
+		ClipboardHandle::new()
+	}
+
+	fn string(&self) -> Result<Option<String>> {
+		// Windows synthesizes CF_TEXT from CF_UNICODETEXT and vice versa, but the Unicode version is lossless.
+		match self.string_unicode()? {
+			Some(string) => Ok(Some(string)),
+			None => (**self).string(),
+		}
+	}
+
+	fn set_string(&self, string: &str) -> Result<()> {
+		(**self).set_string(string)
+	}
+
+	fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		(**self).files()
+	}
+
+	fn set_files(&self, paths: &[PathBuf]) -> Result<()> {
+		(**self).set_files(paths)
+	}
+
+	fn image(&self) -> Result<Option<RgbImage>> {
+		(**self).image()
+	}
+
+	fn get_raw(&self, format: &ClipboardFormat) -> Result<Option<Vec<u8>>> {
+		(**self).get_raw(*format)
+	}
+
+	fn available_formats(&self) -> Result<Vec<ClipboardFormat>> {
+		(**self).available_formats()
+	}
+}
+
+/// Windows-specific functionality that doesn't fit into [`Clipboard`].
+pub trait WindowsClipboardExt {
+	/// Puts raw bytes on the clipboard, as the given format.
+	fn set_raw(&self, format: ClipboardFormat, data: &[u8]) -> Result<()>;
+
+	/// Returns the raw `HWND` that the clipboard is opened with.
+	fn hwnd(&self) -> isize;
+}
+
+impl WindowsClipboardExt for ClipboardHandle {
+	fn set_raw(&self, format: ClipboardFormat, data: &[u8]) -> Result<()> {
+		(**self).set_raw(format, data)
+	}
+
+	fn hwnd(&self) -> isize {
+		self.window().0
+	}
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::Clipboard;
+use clipee_x11::{
+	error::{Error, Result},
+	format::X11ClipboardFormat,
+	ClipboardHandle,
+};
+use image::RgbImage;
+use std::path::PathBuf;
+
+/// The clipboard backend for this platform.
+pub type SystemClipboard = ClipboardHandle;
+
+impl Clipboard for ClipboardHandle {
+	type Error = Error;
+	type Format = X11ClipboardFormat;
+
+	fn system() -> Result<Self> {
+		ClipboardHandle::new()
+	}
+
+	fn string(&self) -> Result<Option<String>> {
+		ClipboardHandle::string(self)
+	}
+
+	fn set_string(&self, string: &str) -> Result<()> {
+		ClipboardHandle::set_string(self, string)
+	}
+
+	fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		ClipboardHandle::files(self)
+	}
+
+	fn set_files(&self, paths: &[PathBuf]) -> Result<()> {
+		ClipboardHandle::set_files(self, paths)
+	}
+
+	fn image(&self) -> Result<Option<RgbImage>> {
+		ClipboardHandle::image(self)
+	}
+
+	fn get_raw(&self, format: &X11ClipboardFormat) -> Result<Option<Vec<u8>>> {
+		ClipboardHandle::get_raw(self, format)
+	}
+
+	fn available_formats(&self) -> Result<Vec<X11ClipboardFormat>> {
+		ClipboardHandle::available_formats(self)
+	}
+}
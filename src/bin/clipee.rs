@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Copies to and pastes from the system clipboard, for use in scripts.
+//!
+//! Exits with 0 on success, 1 on errors, and 2 if the clipboard doesn't have what was asked for.
+
+use clap::{Parser, Subcommand};
+use clipee::Clipboard;
+use image::ImageFormat;
+use std::{
+	error::Error,
+	io::{Read, Write},
+	path::{Path, PathBuf},
+	process::ExitCode,
+	str::FromStr,
+};
+
+#[derive(Parser)]
+#[clap(version, about = "Copies to and pastes from the system clipboard")]
+struct Args {
+	#[clap(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Copies standard input to the clipboard, as text.
+	Copy {
+		/// Copies these files to the clipboard instead.
+		#[clap(long, multiple_values = true, min_values = 1)]
+		files: Option<Vec<PathBuf>>,
+	},
+	/// Prints the text on the clipboard.
+	Paste {
+		/// Prints the files on the clipboard instead, one per line.
+		#[clap(long, conflicts_with_all = &["image", "format"])]
+		files: bool,
+		/// Saves the image on the clipboard to this path instead, as PNG, JPEG, BMP or TIFF, going by its extension.
+		#[clap(long, conflicts_with = "format")]
+		image: Option<PathBuf>,
+		/// Prints the raw bytes of this format instead, as named by the formats command.
+		#[clap(long)]
+		format: Option<String>,
+	},
+	/// Lists the formats that the clipboard's contents are available in.
+	Formats,
+}
+
+/// What a command did, if it didn't fail.
+enum Outcome {
+	Done,
+	/// The clipboard was empty, or didn't have the requested format.
+	Absent,
+}
+
+fn main() -> ExitCode {
+	// clap exits with 2 on usage errors, which we use for an empty clipboard.
+	let args = match Args::try_parse() {
+		Ok(args) => args,
+		Err(err) => {
+			let _ = err.print();
+			return if err.use_stderr() {
+				ExitCode::FAILURE
+			} else {
+				ExitCode::SUCCESS
+			};
+		}
+	};
+	// Checked before opening the clipboard, so that it's reported even if the clipboard is empty or unavailable.
+	if let Command::Paste {
+		image: Some(path), ..
+	} = &args.command
+	{
+		if let Err(err) = check_image_path(path) {
+			eprintln!("clipee: {}", err);
+			return ExitCode::FAILURE;
+		}
+	}
+	let result = clipee::system()
+		.map_err(Box::<dyn Error>::from)
+		.and_then(|clipboard| run(&clipboard, args.command));
+	match result {
+		Ok(Outcome::Done) => ExitCode::SUCCESS,
+		Ok(Outcome::Absent) => ExitCode::from(2),
+		Err(err) => {
+			eprintln!("clipee: {}", err);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn run<C: Clipboard>(clipboard: &C, command: Command) -> Result<Outcome, Box<dyn Error>>
+where
+	<C::Format as FromStr>::Err: Error + 'static,
+{
+	match command {
+		Command::Copy { files: Some(files) } => clipboard.set_files(&files)?,
+		Command::Copy { files: None } => {
+			let mut string = String::new();
+			std::io::stdin().read_to_string(&mut string)?;
+			clipboard.set_string(&string)?;
+		}
+		Command::Paste { files: true, .. } => {
+			let files = match clipboard.files()? {
+				Some(files) => files,
+				None => return Ok(Outcome::Absent),
+			};
+			let mut stdout = std::io::stdout().lock();
+			for file in files {
+				writeln!(stdout, "{}", file.display())?;
+			}
+		}
+		Command::Paste {
+			image: Some(path), ..
+		} => {
+			let image = match clipboard.image()? {
+				Some(image) => image,
+				None => return Ok(Outcome::Absent),
+			};
+			image.save(path)?;
+		}
+		Command::Paste {
+			format: Some(format),
+			..
+		} => {
+			let format = format.parse::<C::Format>()?;
+			let data = match clipboard.get_raw(&format)? {
+				Some(data) => data,
+				None => return Ok(Outcome::Absent),
+			};
+			std::io::stdout().write_all(&data)?;
+		}
+		Command::Paste { .. } => {
+			let string = match clipboard.string()? {
+				Some(string) => string,
+				None => return Ok(Outcome::Absent),
+			};
+			std::io::stdout().write_all(string.as_bytes())?;
+		}
+		Command::Formats => {
+			let mut stdout = std::io::stdout().lock();
+			for format in clipboard.available_formats()? {
+				writeln!(stdout, "{}", format)?;
+			}
+		}
+	}
+	Ok(Outcome::Done)
+}
+
+/// Makes sure that an image can be saved to `path`, which needs the extension of a format that the `cli` feature enables.
+fn check_image_path(path: &Path) -> Result<(), String> {
+	match ImageFormat::from_path(path) {
+		Ok(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Bmp | ImageFormat::Tiff) => Ok(()),
+		_ => Err(format!(
+			"can't save an image to {}, as only .png, .jpg, .bmp and .tiff files are supported",
+			path.display()
+		)),
+	}
+}
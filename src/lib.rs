@@ -1 +1,70 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A uniform clipboard API, backed by the native clipboard of each platform.
+
+mod backend;
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(target_os = "macos")]
+pub use backend::macos::SystemClipboard;
+#[cfg(windows)]
+pub use backend::windows::{SystemClipboard, WindowsClipboardExt};
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use backend::x11::SystemClipboard;
+#[cfg(target_os = "macos")]
+pub use clipee_macos as macos;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use clipee_wayland as wayland;
+#[cfg(windows)]
+pub use clipee_windows as windows;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use clipee_x11 as x11;
+
+use image::RgbImage;
+use std::{
+	fmt::{Debug, Display},
+	path::PathBuf,
+	str::FromStr,
+};
+
+/// The operations that every clipboard backend supports.
+///
+/// Anything platform-specific lives on the backend's own type, or an extension trait such as `WindowsClipboardExt`.
+pub trait Clipboard {
+	type Error: std::error::Error + Send + Sync + 'static;
+	/// The backend's own description of a clipboard format, which can be shown to and parsed from users.
+	type Format: Debug + Display + FromStr;
+
+	/// Opens the system clipboard.
+	fn system() -> Result<Self, Self::Error>
+	where
+		Self: Sized;
+
+	/// Gets the text on the clipboard.
+	fn string(&self) -> Result<Option<String>, Self::Error>;
+
+	/// Replaces the text on the clipboard.
+	fn set_string(&self, string: &str) -> Result<(), Self::Error>;
+
+	/// Gets the list of files on the clipboard.
+	fn files(&self) -> Result<Option<Vec<PathBuf>>, Self::Error>;
+
+	/// Puts a list of files on the clipboard.
+	fn set_files(&self, paths: &[PathBuf]) -> Result<(), Self::Error>;
+
+	/// Gets the image on the clipboard.
+	fn image(&self) -> Result<Option<RgbImage>, Self::Error>;
+
+	/// Gets the raw bytes of a clipboard format.
+	fn get_raw(&self, format: &Self::Format) -> Result<Option<Vec<u8>>, Self::Error>;
+
+	/// Lists the formats that the clipboard's contents are available in.
+	fn available_formats(&self) -> Result<Vec<Self::Format>, Self::Error>;
+}
+
+/// Opens the clipboard of the platform this was compiled for.
+#[cfg(any(windows, unix))]
+pub fn system() -> Result<SystemClipboard, <SystemClipboard as Clipboard>::Error> {
+	SystemClipboard::system()
+}
@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! An in-memory clipboard, for testing code that uses the clipboard without touching the real one.
+
+use crate::Clipboard;
+use image::RgbImage;
+use parking_lot::Mutex;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::{
+	collections::HashMap,
+	convert::Infallible,
+	ffi::OsStr,
+	fmt::{self, Display},
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+pub type Result<T> = std::result::Result<T, MockError>;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MockError {
+	#[error("Failed to decode string as UTF-8: {0}")]
+	InvalidString(std::str::Utf8Error),
+	#[error("Failed to decode string as UTF-16")]
+	InvalidUnicodeString,
+	#[error("A valid image could not be constructed from the clipboard data")]
+	InvalidImage,
+	#[error("No files were given to put on the clipboard")]
+	NoFiles,
+}
+
+/// The formats a [`MockClipboard`] can hold, mirroring the Windows clipboard formats.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MockFormat {
+	/// UTF-8 text, standing in for `CF_TEXT`.
+	Text,
+	/// UTF-16 text, stored as little-endian bytes, standing in for `CF_UNICODETEXT`.
+	UnicodeText,
+	/// A list of paths, standing in for `CF_HDROP`.
+	///
+	/// Each path is kept exactly as the platform encodes it, so paths that aren't valid Unicode survive:
+	/// as raw bytes followed by a null byte on Unix, and as little-endian UTF-16 followed by a null code unit on Windows.
+	DropHandle,
+	/// An RGB image, stored as its little-endian `u32` width and height followed by its pixels, standing in for `CF_DIB`.
+	Bitmap,
+	/// Any other format, identified by name.
+	Custom(String),
+}
+
+/// Formats are shown by the name of the Windows format they stand in for, or by their name if they're custom.
+impl Display for MockFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Text => "CF_TEXT",
+			Self::UnicodeText => "CF_UNICODETEXT",
+			Self::DropHandle => "CF_HDROP",
+			Self::Bitmap => "CF_DIB",
+			Self::Custom(name) => name,
+		})
+	}
+}
+
+impl FromStr for MockFormat {
+	type Err = Infallible;
+
+	fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+		Ok(match name {
+			"CF_TEXT" => Self::Text,
+			"CF_UNICODETEXT" => Self::UnicodeText,
+			"CF_HDROP" => Self::DropHandle,
+			"CF_DIB" => Self::Bitmap,
+			name => Self::Custom(name.to_owned()),
+		})
+	}
+}
+
+/// An in-memory clipboard, with the same surface as the Windows clipboard handle.
+///
+/// Like Windows, this synthesizes some formats from others:
+/// - Reading [`MockFormat::Text`] falls back to [`MockFormat::UnicodeText`], and vice versa.
+/// - Setting text, files or an image replaces everything else, like the Windows backend empties the clipboard first.
+///   [`MockClipboard::set_raw`] only replaces its own format, so it can add formats alongside the others.
+#[derive(Debug, Default)]
+pub struct MockClipboard {
+	formats: Mutex<HashMap<MockFormat, Vec<u8>>>,
+}
+
+impl MockClipboard {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn set_string<StringType: ToString>(&self, string: StringType) -> Result<()> {
+		let bytes = string
+			.to_string()
+			.encode_utf16()
+			.flat_map(u16::to_le_bytes)
+			.collect();
+		self.replace(MockFormat::UnicodeText, bytes);
+		Ok(())
+	}
+
+	pub fn string(&self) -> Result<Option<String>> {
+		match self.get_raw(&MockFormat::Text) {
+			Some(bytes) => std::str::from_utf8(&bytes)
+				.map(|string| Some(string.to_owned()))
+				.map_err(MockError::InvalidString),
+			None => self.unicode_string(),
+		}
+	}
+
+	pub fn string_unicode(&self) -> Result<Option<String>> {
+		match self.unicode_string()? {
+			Some(string) => Ok(Some(string)),
+			None => self.get_raw(&MockFormat::Text).map_or(Ok(None), |bytes| {
+				std::str::from_utf8(&bytes)
+					.map(|string| Some(string.to_owned()))
+					.map_err(MockError::InvalidString)
+			}),
+		}
+	}
+
+	fn unicode_string(&self) -> Result<Option<String>> {
+		let bytes = match self.get_raw(&MockFormat::UnicodeText) {
+			Some(bytes) => bytes,
+			None => return Ok(None),
+		};
+		let units = bytes
+			.chunks_exact(2)
+			.map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+			.collect::<Vec<_>>();
+		String::from_utf16(&units)
+			.map(Some)
+			.map_err(|_| MockError::InvalidUnicodeString)
+	}
+
+	pub fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		let bytes = match self.get_raw(&MockFormat::DropHandle) {
+			Some(bytes) => bytes,
+			None => return Ok(None),
+		};
+		Ok(Some(decode_paths(&bytes)))
+	}
+
+	/// Like the Windows backend, empty paths are skipped, and this fails with [`MockError::NoFiles`]
+	/// if there are none left, rather than putting an empty list on the clipboard.
+	pub fn set_files<I>(&self, paths: I) -> Result<()>
+	where
+		I: IntoIterator,
+		I::Item: AsRef<Path>,
+	{
+		let mut bytes = Vec::new();
+		for path in paths {
+			let path = path.as_ref().as_os_str();
+			if !path.is_empty() {
+				encode_path(path, &mut bytes);
+			}
+		}
+		if bytes.is_empty() {
+			return Err(MockError::NoFiles);
+		}
+		self.replace(MockFormat::DropHandle, bytes);
+		Ok(())
+	}
+
+	pub fn image(&self) -> Result<Option<RgbImage>> {
+		let bytes = match self.get_raw(&MockFormat::Bitmap) {
+			Some(bytes) if bytes.len() >= 8 => bytes,
+			Some(_) => return Err(MockError::InvalidImage),
+			None => return Ok(None),
+		};
+		let width = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+		let height = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+		RgbImage::from_raw(width, height, bytes[8..].to_vec())
+			.map(Some)
+			.ok_or(MockError::InvalidImage)
+	}
+
+	pub fn set_image(&self, image: &RgbImage) -> Result<()> {
+		let mut bytes = Vec::with_capacity(8 + image.as_raw().len());
+		bytes.extend_from_slice(&image.width().to_le_bytes());
+		bytes.extend_from_slice(&image.height().to_le_bytes());
+		bytes.extend_from_slice(image.as_raw());
+		self.replace(MockFormat::Bitmap, bytes);
+		Ok(())
+	}
+
+	/// Gets the raw bytes stored for a format.
+	pub fn get_raw(&self, format: &MockFormat) -> Option<Vec<u8>> {
+		self.formats.lock().get(format).cloned()
+	}
+
+	/// Stores raw bytes for a format, replacing whatever was there before in that format,
+	/// but leaving the other formats alone.
+	pub fn set_raw(&self, format: MockFormat, data: Vec<u8>) {
+		self.formats.lock().insert(format, data);
+	}
+
+	/// Replaces everything with a single format.
+	fn replace(&self, format: MockFormat, data: Vec<u8>) {
+		let mut formats = self.formats.lock();
+		formats.clear();
+		formats.insert(format, data);
+	}
+
+	pub fn empty(&self) -> Result<()> {
+		self.formats.lock().clear();
+		Ok(())
+	}
+
+	pub fn available_formats(&self) -> Result<Vec<MockFormat>> {
+		Ok(self.formats.lock().keys().cloned().collect())
+	}
+}
+
+impl Clipboard for MockClipboard {
+	type Error = MockError;
+	type Format = MockFormat;
+
+	fn system() -> Result<Self> {
+		Ok(Self::new())
+	}
+
+	fn string(&self) -> Result<Option<String>> {
+		self.string_unicode()
+	}
+
+	fn set_string(&self, string: &str) -> Result<()> {
+		MockClipboard::set_string(self, string)
+	}
+
+	fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		MockClipboard::files(self)
+	}
+
+	fn set_files(&self, paths: &[PathBuf]) -> Result<()> {
+		MockClipboard::set_files(self, paths)
+	}
+
+	fn image(&self) -> Result<Option<RgbImage>> {
+		MockClipboard::image(self)
+	}
+
+	fn get_raw(&self, format: &MockFormat) -> Result<Option<Vec<u8>>> {
+		Ok(MockClipboard::get_raw(self, format))
+	}
+
+	fn available_formats(&self) -> Result<Vec<MockFormat>> {
+		MockClipboard::available_formats(self)
+	}
+}
+
+/// Appends a path to a [`MockFormat::DropHandle`] list, as its raw bytes.
+#[cfg(unix)]
+fn encode_path(path: &OsStr, bytes: &mut Vec<u8>) {
+	bytes.extend_from_slice(path.as_bytes());
+	bytes.push(0);
+}
+
+/// Appends a path to a [`MockFormat::DropHandle`] list, as its UTF-16 code units.
+#[cfg(windows)]
+fn encode_path(path: &OsStr, bytes: &mut Vec<u8>) {
+	bytes.extend(
+		path.encode_wide()
+			.chain(std::iter::once(0))
+			.flat_map(u16::to_le_bytes),
+	);
+}
+
+/// Splits a [`MockFormat::DropHandle`] list back into paths.
+#[cfg(unix)]
+fn decode_paths(bytes: &[u8]) -> Vec<PathBuf> {
+	bytes
+		.split(|byte| *byte == 0)
+		.filter(|path| !path.is_empty())
+		.map(|path| PathBuf::from(OsStr::from_bytes(path)))
+		.collect()
+}
+
+/// Splits a [`MockFormat::DropHandle`] list back into paths.
+#[cfg(windows)]
+fn decode_paths(bytes: &[u8]) -> Vec<PathBuf> {
+	let units = bytes
+		.chunks_exact(2)
+		.map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+		.collect::<Vec<_>>();
+	units
+		.split(|unit| *unit == 0)
+		.filter(|path| !path.is_empty())
+		.map(|path| PathBuf::from(std::ffi::OsString::from_wide(path)))
+		.collect()
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(feature = "cli", any(windows, unix)))]
+
+use assert_cmd::Command;
+
+#[test]
+pub fn copy_then_paste() {
+	// Headless machines, such as CI without an X server, have no clipboard to copy to.
+	if let Err(err) = clipee::system() {
+		eprintln!("skipping, as the system clipboard can't be opened: {}", err);
+		return;
+	}
+	Command::cargo_bin("clipee")
+		.expect("failed to find binary")
+		.arg("copy")
+		.write_stdin("copied from the command line")
+		.assert()
+		.success();
+	Command::cargo_bin("clipee")
+		.expect("failed to find binary")
+		.arg("paste")
+		.assert()
+		.success()
+		.stdout("copied from the command line");
+	// Plain text isn't a list of files, which scripts can tell apart from a real error.
+	Command::cargo_bin("clipee")
+		.expect("failed to find binary")
+		.args(["paste", "--files"])
+		.assert()
+		.code(2);
+}
+
+#[test]
+pub fn rejects_unknown_subcommand() {
+	Command::cargo_bin("clipee")
+		.expect("failed to find binary")
+		.arg("cut")
+		.assert()
+		.code(1);
+}
+
+#[test]
+pub fn rejects_unsupported_image_extension() {
+	let assert = Command::cargo_bin("clipee")
+		.expect("failed to find binary")
+		.args(["paste", "--image", "out.xyz"])
+		.assert()
+		.code(1);
+	let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+	assert!(
+		stderr.contains("only .png, .jpg, .bmp and .tiff"),
+		"unexpected error: {}",
+		stderr
+	);
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(any(windows, unix))]
+
+use clipee::Clipboard;
+
+#[test]
+pub fn round_trip_string_through_trait() {
+	let clipboard = clipee::system().expect("failed to open clipboard");
+	clipboard
+		.set_string("facade test")
+		.expect("failed to set string to clipboard");
+	let result = Clipboard::string(&clipboard)
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(result, "facade test");
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(feature = "mock")]
+
+use clipee::{
+	mock::{MockClipboard, MockError, MockFormat},
+	Clipboard,
+};
+use image::{Rgb, RgbImage};
+use std::path::PathBuf;
+
+#[test]
+pub fn mock_string() {
+	let clipboard = MockClipboard::new();
+	assert_eq!(clipboard.string_unicode(), Ok(None));
+	clipboard
+		.set_string("mock 🦀")
+		.expect("failed to set string");
+	assert_eq!(clipboard.string_unicode(), Ok(Some("mock 🦀".to_owned())));
+	// CF_TEXT reads are satisfied by CF_UNICODETEXT.
+	assert_eq!(clipboard.string(), Ok(Some("mock 🦀".to_owned())));
+	assert_eq!(
+		clipboard.available_formats(),
+		Ok(vec![MockFormat::UnicodeText])
+	);
+}
+
+#[test]
+pub fn mock_files() {
+	let clipboard = MockClipboard::new();
+	let files = vec![PathBuf::from("C:\\Users\\"), PathBuf::from("C:\\test.txt")];
+	clipboard.set_files(&files).expect("failed to set files");
+	assert_eq!(clipboard.files(), Ok(Some(files.clone())));
+	// Like on Windows, empty paths are skipped, and an empty list is rejected rather than replacing the files.
+	assert_eq!(
+		clipboard.set_files([PathBuf::new()]),
+		Err(MockError::NoFiles)
+	);
+	assert_eq!(clipboard.files(), Ok(Some(files)));
+}
+
+#[test]
+#[cfg(unix)]
+pub fn mock_non_utf8_files() {
+	use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+	let clipboard = MockClipboard::new();
+	let files = vec![PathBuf::from(OsStr::from_bytes(b"/tmp/caf\xE9"))];
+	clipboard.set_files(&files).expect("failed to set files");
+	assert_eq!(clipboard.files(), Ok(Some(files)));
+}
+
+#[test]
+#[cfg(windows)]
+pub fn mock_non_utf8_files() {
+	use std::{ffi::OsString, os::windows::ffi::OsStringExt};
+
+	let clipboard = MockClipboard::new();
+	// "C:\a" followed by an unpaired surrogate.
+	let files = vec![PathBuf::from(OsString::from_wide(&[
+		0x43, 0x3A, 0x5C, 0x61, 0xD800,
+	]))];
+	clipboard.set_files(&files).expect("failed to set files");
+	assert_eq!(clipboard.files(), Ok(Some(files)));
+}
+
+#[test]
+pub fn mock_image_and_empty() {
+	let clipboard = MockClipboard::new();
+	let image = RgbImage::from_pixel(2, 3, Rgb([1, 2, 3]));
+	clipboard.set_image(&image).expect("failed to set image");
+	assert_eq!(Clipboard::image(&clipboard), Ok(Some(image)));
+	clipboard.empty().expect("failed to empty");
+	assert_eq!(clipboard.image(), Ok(None));
+	assert_eq!(clipboard.available_formats(), Ok(vec![]));
+}
+
+#[test]
+pub fn mock_setters_replace() {
+	let clipboard = MockClipboard::new();
+	clipboard.set_string("text").expect("failed to set string");
+	clipboard
+		.set_image(&RgbImage::new(1, 1))
+		.expect("failed to set image");
+	assert_eq!(clipboard.string_unicode(), Ok(None));
+	assert_eq!(clipboard.available_formats(), Ok(vec![MockFormat::Bitmap]));
+	// Raw formats are added alongside what's there already.
+	clipboard.set_raw(MockFormat::Custom("custom".to_owned()), b"raw".to_vec());
+	assert_eq!(
+		clipboard.available_formats().map(|formats| formats.len()),
+		Ok(2)
+	);
+}
+
+#[test]
+pub fn mock_format_names() {
+	let clipboard = MockClipboard::new();
+	clipboard.set_raw(MockFormat::Custom("custom".to_owned()), b"raw".to_vec());
+	for format in [
+		MockFormat::Text,
+		MockFormat::UnicodeText,
+		MockFormat::DropHandle,
+		MockFormat::Bitmap,
+		MockFormat::Custom("custom".to_owned()),
+	] {
+		assert_eq!(format.to_string().parse(), Ok(format));
+	}
+	let format = "custom"
+		.parse::<MockFormat>()
+		.expect("failed to parse format");
+	assert_eq!(
+		Clipboard::get_raw(&clipboard, &format),
+		Ok(Some(b"raw".to_vec()))
+	);
+}
@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use wayland_client::{
+	globals::{BindError, GlobalError},
+	ConnectError, DispatchError,
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+	#[error("Failed to connect to the Wayland compositor: {0}")]
+	Connect(String),
+	#[error("Lost connection to the Wayland compositor: {0}")]
+	Connection(String),
+	#[error("The compositor doesn't support the wlr-data-control protocol")]
+	UnsupportedCompositor,
+	#[error("The compositor doesn't have a seat to get the clipboard of")]
+	NoSeat,
+	#[error("Timed out waiting for the compositor")]
+	Timeout,
+	#[error("Failed to transfer clipboard data: {0}")]
+	Transfer(String),
+	#[error("Failed to decode string as UTF-8: {0}")]
+	InvalidString(std::str::Utf8Error),
+	#[error("{0:?} is not a valid file URI")]
+	FileUri(String),
+	#[error("A valid image could not be constructed from the clipboard data")]
+	InvalidImage,
+}
+
+// wayland-client's errors can't be cloned or compared, so only their messages are kept.
+
+impl From<ConnectError> for Error {
+	fn from(err: ConnectError) -> Self {
+		Self::Connect(err.to_string())
+	}
+}
+
+impl From<GlobalError> for Error {
+	fn from(err: GlobalError) -> Self {
+		Self::Connection(err.to_string())
+	}
+}
+
+impl From<DispatchError> for Error {
+	fn from(err: DispatchError) -> Self {
+		Self::Connection(err.to_string())
+	}
+}
+
+impl From<wayland_client::backend::WaylandError> for Error {
+	fn from(err: wayland_client::backend::WaylandError) -> Self {
+		Self::Connection(err.to_string())
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Self::Transfer(err.to_string())
+	}
+}
+
+impl From<BindError> for Error {
+	fn from(_: BindError) -> Self {
+		Self::UnsupportedCompositor
+	}
+}
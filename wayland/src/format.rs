@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Wayland identifies clipboard formats by MIME type; these are the ones that we read and write ourselves.
+
+pub(crate) mod uri_list;
+
+/// UTF-8 text.
+pub const TEXT: &str = "text/plain;charset=utf-8";
+/// Other names that clients offer UTF-8 text under, in order of preference.
+pub const TEXT_ALIASES: &[&str] = &["UTF8_STRING", "text/plain", "STRING", "TEXT"];
+/// A list of URIs, one per line.
+pub const URI_LIST: &str = "text/uri-list";
+/// A PNG image.
+pub const PNG: &str = "image/png";
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! `text/uri-list`, as described in RFC 2483.
+
+use crate::error::{Error, Result};
+use percent_encoding::{percent_decode, percent_encode, AsciiSet, CONTROLS};
+use std::{
+	ffi::OsStr,
+	os::unix::ffi::OsStrExt,
+	path::{Path, PathBuf},
+};
+
+/// Everything that isn't allowed to appear as-is in the path of a URI.
+const PATH: &AsciiSet = &CONTROLS
+	.add(b' ')
+	.add(b'"')
+	.add(b'#')
+	.add(b'%')
+	.add(b'<')
+	.add(b'>')
+	.add(b'?')
+	.add(b'[')
+	.add(b'\\')
+	.add(b']')
+	.add(b'^')
+	.add(b'`')
+	.add(b'{')
+	.add(b'|')
+	.add(b'}');
+
+/// Parses a list of `file://` URIs into paths.
+pub(crate) fn parse(list: &[u8]) -> Result<Vec<PathBuf>> {
+	list.split(|byte| *byte == b'\n')
+		.map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+		// Lines starting with a # are comments.
+		.filter(|line| !line.is_empty() && !line.starts_with(b"#"))
+		.map(path_from_uri)
+		.collect()
+}
+
+/// Encodes a list of paths as `file://` URIs.
+pub(crate) fn encode<PathType: AsRef<Path>>(paths: &[PathType]) -> Vec<u8> {
+	let mut list = Vec::new();
+	for path in paths {
+		list.extend_from_slice(b"file://");
+		list.extend(
+			percent_encode(path.as_ref().as_os_str().as_bytes(), PATH).flat_map(str::bytes),
+		);
+		list.extend_from_slice(b"\r\n");
+	}
+	list
+}
+
+fn path_from_uri(uri: &[u8]) -> Result<PathBuf> {
+	let invalid = || Error::FileUri(String::from_utf8_lossy(uri).into_owned());
+	let rest = uri.strip_prefix(b"file://").ok_or_else(invalid)?;
+	// Skip over the host, which is usually either empty or "localhost".
+	let start = rest
+		.iter()
+		.position(|byte| *byte == b'/')
+		.ok_or_else(invalid)?;
+	let path = percent_decode(&rest[start..]).collect::<Vec<u8>>();
+	Ok(PathBuf::from(OsStr::from_bytes(&path)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let paths = [
+			PathBuf::from("/home/clipboard/test.txt"),
+			PathBuf::from("/home/clipboard/with space/100%.txt"),
+			PathBuf::from("/tmp/ünïcödé#?.txt"),
+		];
+		let list = encode(&paths);
+		assert_eq!(parse(&list).expect("failed to parse uri list"), paths);
+	}
+
+	#[test]
+	fn parse_hosts_and_comments() {
+		let list = b"# copied from a file manager\nfile:///tmp/a%20b\nfile://localhost/tmp/c\n";
+		assert_eq!(
+			parse(list).expect("failed to parse uri list"),
+			[PathBuf::from("/tmp/a b"), PathBuf::from("/tmp/c")]
+		);
+	}
+
+	#[test]
+	fn reject_other_schemes() {
+		assert_eq!(
+			parse(b"https://example.com/file.txt"),
+			Err(Error::FileUri("https://example.com/file.txt".to_owned()))
+		);
+	}
+}
@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+pub mod error;
+pub mod format;
+pub(crate) mod state;
+
+use self::{
+	error::{Error, Result},
+	format::uri_list,
+	state::{Contents, Shared, State},
+};
+use parking_lot::Mutex;
+use std::{
+	fs::File,
+	io::Read,
+	os::fd::AsFd,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc, Arc,
+	},
+	thread::JoinHandle,
+	time::Duration,
+};
+use wayland_client::{
+	globals::registry_queue_init, protocol::wl_seat::WlSeat, Connection, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+	zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+	zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+};
+
+/// How long to wait for the compositor to answer a round-trip.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A handle to the Wayland clipboard, through the wlr-data-control protocol.
+///
+/// Unlike the core protocol, data-control doesn't need a focused surface,
+/// so this works from daemons and command line tools.
+/// Events are handled on a background thread, which keeps our selection available
+/// for as long as any clone of this handle is alive.
+#[derive(Clone)]
+pub struct ClipboardHandle(Arc<ClipboardHandleInner>);
+
+struct ClipboardHandleInner {
+	conn: Connection,
+	queue: QueueHandle<State>,
+	manager: ZwlrDataControlManagerV1,
+	device: ZwlrDataControlDeviceV1,
+	shared: Arc<Mutex<Shared>>,
+	running: Arc<AtomicBool>,
+	thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ClipboardHandle {
+	/// Connects to the compositor named by the environment.
+	///
+	/// Fails with [`Error::UnsupportedCompositor`] if the compositor doesn't implement wlr-data-control,
+	/// in which case another backend should be used instead.
+	pub fn new() -> Result<Self> {
+		let conn = Connection::connect_to_env()?;
+		let (globals, mut event_queue) = registry_queue_init::<State>(&conn)?;
+		let queue = event_queue.handle();
+		let manager = globals.bind::<ZwlrDataControlManagerV1, _, _>(&queue, 1..=2, ())?;
+		let seat = globals
+			.bind::<WlSeat, _, _>(&queue, 1..=1, ())
+			.map_err(|_| Error::NoSeat)?;
+		let device = manager.get_data_device(&seat, &queue, ());
+		let shared = Arc::new(Mutex::new(Shared::default()));
+		let mut state = State {
+			shared: shared.clone(),
+		};
+		// Get the current selection before anyone asks for it.
+		event_queue.roundtrip(&mut state)?;
+		let running = Arc::new(AtomicBool::new(true));
+		let thread = std::thread::Builder::new()
+			.name("clipee-wayland event loop".to_owned())
+			.spawn({
+				let running = running.clone();
+				move || {
+					while running.load(Ordering::SeqCst) {
+						if event_queue.blocking_dispatch(&mut state).is_err() {
+							break;
+						}
+					}
+				}
+			})
+			.expect("failed to spawn event loop thread");
+		Ok(Self(Arc::new(ClipboardHandleInner {
+			conn,
+			queue,
+			manager,
+			device,
+			shared,
+			running,
+			thread: Mutex::new(Some(thread)),
+		})))
+	}
+
+	pub fn set_string<StringType: ToString>(&self, string: StringType) -> Result<()> {
+		let string = Arc::<[u8]>::from(string.to_string().into_bytes());
+		self.set_mime_types(Contents::new(
+			std::iter::once(format::TEXT)
+				.chain(format::TEXT_ALIASES.iter().copied())
+				.map(|mime_type| (mime_type.to_owned(), string.clone()))
+				.collect(),
+		))
+	}
+
+	pub fn string(&self) -> Result<Option<String>> {
+		for mime_type in std::iter::once(format::TEXT).chain(format::TEXT_ALIASES.iter().copied()) {
+			if let Some(bytes) = self.get_raw(mime_type)? {
+				return String::from_utf8(bytes)
+					.map(Some)
+					.map_err(|err| Error::InvalidString(err.utf8_error()));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Gets the list of files on the clipboard, from `text/uri-list`.
+	pub fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		match self.get_raw(format::URI_LIST)? {
+			Some(list) => uri_list::parse(&list).map(Some),
+			None => Ok(None),
+		}
+	}
+
+	pub fn set_files<PathType: AsRef<Path>, PathList: AsRef<[PathType]>>(
+		&self,
+		paths: PathList,
+	) -> Result<()> {
+		let list = uri_list::encode(paths.as_ref());
+		self.set_raw(format::URI_LIST, &list)
+	}
+
+	/// Gets the image on the clipboard, from `image/png`.
+	pub fn image(&self) -> Result<Option<image::RgbImage>> {
+		match self.get_raw(format::PNG)? {
+			Some(png) => image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+				.map(|image| Some(image.to_rgb8()))
+				.map_err(|_| Error::InvalidImage),
+			None => Ok(None),
+		}
+	}
+
+	/// Gets the clipboard contents as the given MIME type, if the selection is offered as it.
+	pub fn get_raw(&self, mime_type: &str) -> Result<Option<Vec<u8>>> {
+		// The lock mustn't be held while reading, as we may be the ones sending the data.
+		let offer = match self.0.shared.lock().selection_as(mime_type) {
+			Some(offer) => offer,
+			None => return Ok(None),
+		};
+		let (reader, writer) = rustix::pipe::pipe().map_err(std::io::Error::from)?;
+		offer.receive(mime_type.to_owned(), writer.as_fd());
+		self.0.conn.flush()?;
+		// Our copy of the write end has to be closed, or we'd never see the end of the data.
+		drop(writer);
+		let mut data = Vec::new();
+		File::from(reader).read_to_end(&mut data)?;
+		Ok(Some(data))
+	}
+
+	/// Sets the selection, offering raw bytes as the given MIME type.
+	pub fn set_raw(&self, mime_type: &str, data: &[u8]) -> Result<()> {
+		self.set_mime_types(Contents::new(
+			[(mime_type.to_owned(), Arc::from(data))]
+				.into_iter()
+				.collect(),
+		))
+	}
+
+	pub fn empty(&self) -> Result<()> {
+		self.0.device.set_selection(None);
+		self.sync()
+	}
+
+	/// Lists the MIME types that the current selection is offered as.
+	pub fn available_formats(&self) -> Result<Vec<String>> {
+		Ok(self.0.shared.lock().selection_mime_types())
+	}
+
+	fn set_mime_types(&self, contents: Contents) -> Result<()> {
+		let source = self.0.manager.create_data_source(&self.0.queue, ());
+		for mime_type in contents.keys() {
+			source.offer(mime_type.clone());
+		}
+		self.0.shared.lock().sources.insert(source.id(), contents);
+		self.0.device.set_selection(Some(&source));
+		self.sync()
+	}
+
+	/// Waits for the compositor to process everything we've sent, and for us to process everything it's sent back,
+	/// so that our view of the selection is up to date.
+	fn sync(&self) -> Result<()> {
+		let (done, wait) = mpsc::sync_channel(1);
+		self.0.conn.display().sync(&self.0.queue, Some(done));
+		self.0.conn.flush()?;
+		wait.recv_timeout(SYNC_TIMEOUT).map_err(|_| Error::Timeout)
+	}
+}
+
+impl Drop for ClipboardHandleInner {
+	fn drop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+		// Wake the thread up, so it notices that it should stop.
+		self.conn.display().sync(&self.queue, None);
+		if self.conn.flush().is_ok() {
+			if let Some(thread) = self.thread.lock().take() {
+				let _ = thread.join();
+			}
+		}
+		self.device.destroy();
+		let _ = self.conn.flush();
+	}
+}
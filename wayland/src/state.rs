@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The event handlers for the background thread that keeps our view of the selection up to date,
+//! and sends our own selection to whoever asks for it.
+
+use parking_lot::Mutex;
+use std::{collections::HashMap, fs::File, io::Write, sync::mpsc::SyncSender, sync::Arc};
+use wayland_client::{
+	backend::ObjectId,
+	event_created_child,
+	globals::GlobalListContents,
+	protocol::{wl_callback::WlCallback, wl_registry::WlRegistry, wl_seat::WlSeat},
+	Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+	zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+	zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+	zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+	zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+};
+
+/// The data we're offering, by MIME type.
+pub(crate) type Contents = Arc<HashMap<String, Arc<[u8]>>>;
+
+#[derive(Default)]
+pub(crate) struct Shared {
+	/// The offer for the current selection, if there is one.
+	pub selection: Option<ZwlrDataControlOfferV1>,
+	/// The MIME types of every offer we know about.
+	pub offers: HashMap<ObjectId, Vec<String>>,
+	/// The contents of each of our own sources that haven't been cancelled yet.
+	pub sources: HashMap<ObjectId, Contents>,
+}
+
+impl Shared {
+	/// Returns the current selection, if it's available as the given MIME type.
+	pub fn selection_as(&self, mime_type: &str) -> Option<ZwlrDataControlOfferV1> {
+		let selection = self.selection.as_ref()?;
+		self.offers
+			.get(&selection.id())?
+			.iter()
+			.any(|offered| offered == mime_type)
+			.then(|| selection.clone())
+	}
+
+	/// Returns the MIME types of the current selection.
+	pub fn selection_mime_types(&self) -> Vec<String> {
+		self.selection
+			.as_ref()
+			.and_then(|selection| self.offers.get(&selection.id()))
+			.cloned()
+			.unwrap_or_default()
+	}
+}
+
+pub(crate) struct State {
+	pub shared: Arc<Mutex<Shared>>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+	fn event(
+		_: &mut Self,
+		_: &WlRegistry,
+		_: <WlRegistry as Proxy>::Event,
+		_: &GlobalListContents,
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+	}
+}
+
+impl Dispatch<WlSeat, ()> for State {
+	fn event(
+		_: &mut Self,
+		_: &WlSeat,
+		_: <WlSeat as Proxy>::Event,
+		_: &(),
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+	}
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
+	fn event(
+		_: &mut Self,
+		_: &ZwlrDataControlManagerV1,
+		_: <ZwlrDataControlManagerV1 as Proxy>::Event,
+		_: &(),
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+	}
+}
+
+/// Callbacks are used both to wait for the compositor to catch up, and to wake the thread up.
+impl Dispatch<WlCallback, Option<SyncSender<()>>> for State {
+	fn event(
+		_: &mut Self,
+		_: &WlCallback,
+		_: <WlCallback as Proxy>::Event,
+		done: &Option<SyncSender<()>>,
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+		if let Some(done) = done {
+			let _ = done.try_send(());
+		}
+	}
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+	fn event(
+		state: &mut Self,
+		_: &ZwlrDataControlDeviceV1,
+		event: zwlr_data_control_device_v1::Event,
+		_: &(),
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+		let mut shared = state.shared.lock();
+		match event {
+			// The MIME types of a new offer are sent right after this, and before it's used as a selection.
+			zwlr_data_control_device_v1::Event::DataOffer { id } => {
+				shared.offers.insert(id.id(), Vec::new());
+			}
+			zwlr_data_control_device_v1::Event::Selection { id } => {
+				if let Some(old) = std::mem::replace(&mut shared.selection, id) {
+					shared.offers.remove(&old.id());
+					old.destroy();
+				}
+			}
+			// We only care about the regular clipboard.
+			zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+				shared.offers.remove(&offer.id());
+				offer.destroy();
+			}
+			_ => {}
+		}
+	}
+
+	event_created_child!(State, ZwlrDataControlDeviceV1, [
+		zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+	]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+	fn event(
+		state: &mut Self,
+		offer: &ZwlrDataControlOfferV1,
+		event: zwlr_data_control_offer_v1::Event,
+		_: &(),
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+		if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+			state
+				.shared
+				.lock()
+				.offers
+				.entry(offer.id())
+				.or_default()
+				.push(mime_type);
+		}
+	}
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, ()> for State {
+	fn event(
+		state: &mut Self,
+		source: &ZwlrDataControlSourceV1,
+		event: zwlr_data_control_source_v1::Event,
+		_: &(),
+		_: &Connection,
+		_: &QueueHandle<Self>,
+	) {
+		match event {
+			zwlr_data_control_source_v1::Event::Send { mime_type, fd } => {
+				let data = state
+					.shared
+					.lock()
+					.sources
+					.get(&source.id())
+					.and_then(|contents| contents.get(&mime_type))
+					.cloned();
+				if let Some(data) = data {
+					// The requestor may be slow to read, which mustn't hold up the event loop.
+					std::thread::spawn(move || {
+						let _ = File::from(fd).write_all(&data);
+					});
+				}
+			}
+			// Someone else has set the selection, so our data won't be asked for anymore.
+			zwlr_data_control_source_v1::Event::Cancelled => {
+				state.shared.lock().sources.remove(&source.id());
+				source.destroy();
+			}
+			_ => {}
+		}
+	}
+}
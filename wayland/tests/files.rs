@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use clipee_wayland::ClipboardHandle;
+use std::path::PathBuf;
+
+#[test]
+pub fn round_trip_files() {
+	let handle = ClipboardHandle::new().expect("failed to connect to the compositor");
+	let files_list = vec![
+		PathBuf::from("/home/clipboard"),
+		PathBuf::from("/home/clipboard/with space.txt"),
+		PathBuf::from("/tmp/ünïcödé.txt"),
+	];
+	handle
+		.set_files(&files_list)
+		.expect("failed to set files to clipboard");
+	let files = handle
+		.files()
+		.expect("failed to get files from clipboard")
+		.expect("files weren't set in clipboard");
+	assert_eq!(files_list, files, "File list didn't survive the round-trip");
+}
@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use clipee_wayland::ClipboardHandle;
+
+#[test]
+pub fn round_trip_raw() {
+	// Bigger than a pipe's buffer, so this only works if the data is sent off the event loop thread.
+	let data = (0..=255_u8).cycle().take(1024 * 1024).collect::<Vec<u8>>();
+	let handle = ClipboardHandle::new().expect("failed to connect to the compositor");
+	handle
+		.set_raw("application/x-clipee-test", &data)
+		.expect("failed to set data to clipboard");
+	let result = handle
+		.get_raw("application/x-clipee-test")
+		.expect("failed to get data from clipboard")
+		.expect("data wasn't set in clipboard");
+	assert!(data == result, "Data didn't survive round-trip!");
+	assert_eq!(
+		handle
+			.get_raw("application/x-clipee-missing")
+			.expect("failed to get data from clipboard"),
+		None
+	);
+}
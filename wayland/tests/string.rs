@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use clipee_wayland::{format, ClipboardHandle};
+
+#[test]
+pub fn round_trip_string() {
+	let text = "héllo, compositor 🦀";
+	let handle = ClipboardHandle::new().expect("failed to connect to the compositor");
+	handle
+		.set_string(text)
+		.expect("failed to set string to clipboard");
+	let result = handle
+		.string()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(text, result, "String didn't survive round-trip!");
+	let formats = handle
+		.available_formats()
+		.expect("failed to get available formats");
+	assert!(formats.iter().any(|mime_type| mime_type == format::TEXT));
+	handle.empty().expect("failed to empty clipboard");
+	assert_eq!(
+		handle
+			.string()
+			.expect("failed to get string from clipboard"),
+		None
+	);
+}
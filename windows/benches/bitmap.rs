@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::exclusive::ExclusiveClipboard;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use image::RgbImage;
+
+fn decode(c: &mut Criterion) {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let mut group = c.benchmark_group("bitmap");
+	group.sample_size(10);
+	// A 4K screenshot, and an odd width whose rows are padded, so compacting them does some work.
+	for (width, height) in [(3840, 2160), (3839, 2160)] {
+		let image = RgbImage::from_fn(width, height, |x, y| {
+			image::Rgb([x as u8, y as u8, (x ^ y) as u8])
+		});
+		handle
+			.set_image(image)
+			.expect("failed to set image to clipboard");
+		group.throughput(Throughput::Bytes(u64::from(width * height * 3)));
+		group.bench_function(format!("image {}x{}", width, height), |b| {
+			b.iter(|| {
+				handle
+					.image()
+					.expect("failed to get image from clipboard")
+					.expect("image wasn't set in clipboard")
+			})
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::exclusive::ExclusiveClipboard;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The size of the string that's copied, which is about what a big log file comes to.
+const SIZE: usize = 50 * 1024 * 1024;
+
+/// Keeps track of the most heap memory that was in use at once.
+struct PeakAlloc {
+	current: AtomicUsize,
+	peak: AtomicUsize,
+}
+
+impl PeakAlloc {
+	/// Starts measuring the peak from whatever's in use right now.
+	fn reset(&self) {
+		self.peak
+			.store(self.current.load(Ordering::SeqCst), Ordering::SeqCst);
+	}
+
+	/// Returns the most memory that was in use at once since the last reset, on top of what was in use then.
+	fn peak_since(&self, start: usize) -> usize {
+		self.peak.load(Ordering::SeqCst).saturating_sub(start)
+	}
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		let ptr = System.alloc(layout);
+		if !ptr.is_null() {
+			let current = self.current.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+			self.peak.fetch_max(current, Ordering::SeqCst);
+		}
+		ptr
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout);
+		self.current.fetch_sub(layout.size(), Ordering::SeqCst);
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc {
+	current: AtomicUsize::new(0),
+	peak: AtomicUsize::new(0),
+};
+
+fn set_string(c: &mut Criterion) {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	// Mostly ASCII, with some characters outside the BMP, so both kinds of encoding are exercised.
+	let string = "clipee 🦀 ".repeat(SIZE / "clipee 🦀 ".len());
+
+	// The global memory that the string is encoded into isn't on the heap,
+	// so this is everything set_string allocates on top of it.
+	let start = ALLOCATOR.current.load(Ordering::SeqCst);
+	ALLOCATOR.reset();
+	handle
+		.set_string(&string)
+		.expect("failed to set string to clipboard");
+	println!(
+		"peak heap usage while setting a {} byte string: {} bytes",
+		string.len(),
+		ALLOCATOR.peak_since(start)
+	);
+
+	let mut group = c.benchmark_group("string");
+	group
+		.sample_size(10)
+		.throughput(Throughput::Bytes(string.len() as u64));
+	group.bench_function("set_string 50MB", |b| {
+		b.iter(|| {
+			handle
+				.set_string(&string)
+				.expect("failed to set string to clipboard")
+		})
+	});
+	group.bench_function("string_unicode 50MB", |b| {
+		b.iter(|| {
+			handle
+				.string_unicode()
+				.expect("failed to get string from clipboard")
+				.expect("string wasn't set in clipboard")
+		})
+	});
+	group.finish();
+}
+
+criterion_group!(benches, set_string);
+criterion_main!(benches);
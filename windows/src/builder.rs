@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{error::Result, ClipboardHandle};
+use std::time::Duration;
+
+/// Options of a clipboard handle, which are copied into its clones.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ClipboardOptions {
+	pub max_read_size: Option<usize>,
+	pub read_timeout: Option<Duration>,
+}
+
+/// Configures a [`ClipboardHandle`].
+///
+/// Every handle shares the same open clipboard, but the options only apply to the built handle and its clones,
+/// so other handles keep their own.
+#[derive(Debug, Clone, Default)]
+pub struct ClipboardHandleBuilder {
+	options: ClipboardOptions,
+}
+
+impl ClipboardHandleBuilder {
+	/// Sets the maximum number of bytes that will be read from a single clipboard format.
+	///
+	/// Reading anything bigger returns [`Error::PayloadTooLarge`](crate::error::Error::PayloadTooLarge),
+	/// which protects against other programs putting absurdly large objects on the clipboard.
+	/// By default, there is no limit.
+	pub fn max_read_size(mut self, bytes: usize) -> Self {
+		self.options.max_read_size = Some(bytes);
+		self
+	}
+
+	/// Sets how long reads will wait for the clipboard's owner to provide the data,
+	/// before giving up with [`Error::Timeout`](crate::error::Error::Timeout).
+	///
+	/// Programs can promise data without providing it until it's asked for, so a read from a hung program
+	/// would otherwise block forever. With a timeout, reads of data owned by another thread first check
+	/// that the owner is responding to messages, and give up if it isn't.
+	/// The clipboard stays open with the handle throughout, so the handle can still be used after a timeout.
+	///
+	/// There's no telling whether the owner has rendered the data already, so a hung owner's data is given up on
+	/// either way, and an owner that responds but then hangs while rendering still blocks the read.
+	/// By default, reads wait for as long as it takes.
+	pub fn read_timeout(mut self, timeout: Duration) -> Self {
+		self.options.read_timeout = Some(timeout);
+		self
+	}
+
+	pub fn build(self) -> Result<ClipboardHandle> {
+		ClipboardHandle::get_or_create(self.options)
+	}
+}
@@ -1,9 +1,17 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+#[cfg(feature = "image")]
+mod image;
 mod windows;
 
+#[cfg(feature = "image")]
+pub use self::image::ImageError;
 pub use self::windows::WindowsError;
 
+use crate::format::ClipboardFormat;
+#[cfg(feature = "image")]
+use std::path::PathBuf;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
@@ -12,30 +20,146 @@ pub enum Error {
 	ClipboardAlreadyOpen,
 	#[error("Failed to allocate global object: {0}")]
 	Allocation(WindowsError),
+	#[error("Allocating {elements} elements of {element_size} bytes each would overflow")]
+	AllocationOverflow {
+		elements: usize,
+		element_size: usize,
+	},
+	#[error("Can't allocate a global object of zero bytes")]
+	EmptyAllocation,
+	#[error("Global object is {actual} bytes, but at least {needed} bytes are needed")]
+	AllocationTooSmall { needed: usize, actual: usize },
+	#[error("Clipboard data is {size} bytes, which is over the limit of {limit} bytes")]
+	PayloadTooLarge { size: usize, limit: usize },
 	#[error("Failed to lock global object: {0}")]
 	Locking(WindowsError),
 	#[error("Global object is invalid or discarded: {0}")]
 	InvalidObject(WindowsError),
 	#[error("Failed to open clipboard: {0}")]
 	OpenClipboard(WindowsError),
-	#[error("Failed to get clipboard data: {0}")]
-	GetClipboard(WindowsError),
-	#[error("Failed to set clipboard data: {0}")]
-	SetClipboard(WindowsError),
+	#[error("Failed to get {format} clipboard data: {err}")]
+	GetClipboard {
+		format: ClipboardFormat,
+		err: WindowsError,
+	},
+	#[error("Failed to set {format} clipboard data: {err}")]
+	SetClipboard {
+		format: ClipboardFormat,
+		err: WindowsError,
+	},
+	#[error("Failed to empty clipboard: {0}")]
+	EmptyClipboard(WindowsError),
+	#[error("Failed to get bitmap information: {0}")]
+	BitmapObject(WindowsError),
+	#[cfg(feature = "image")]
 	#[error("Failed to get pixels from bitmap: {0}")]
 	ImageBits(WindowsError),
+	#[cfg(feature = "image")]
 	#[error("A valid image could not be constructed from the clipboard data")]
 	InvalidImage,
+	#[cfg(feature = "image")]
+	#[error("{0:?} doesn't have the extension of an image format that can be saved")]
+	ImageExtension(PathBuf),
+	#[cfg(feature = "image")]
+	#[error("Failed to save image: {0}")]
+	SaveImage(#[source] ImageError),
+	#[cfg(feature = "image")]
+	#[error("Failed to read image file: {0}")]
+	OpenImage(#[source] ImageError),
+	#[cfg(feature = "image")]
+	#[error("Failed to decode image file: {0}")]
+	DecodeImage(#[source] ImageError),
+	#[error("Failed to read enhanced metafile: {0}")]
+	ReadMetafile(WindowsError),
+	#[error("Failed to create enhanced metafile: {0}")]
+	CreateMetafile(WindowsError),
+	#[error("Audio data is not a RIFF/WAVE file")]
+	InvalidWave,
+	#[error("An empty URL can't be put on the clipboard")]
+	EmptyUrl,
+	#[error("No files were given to put on the clipboard")]
+	NoFiles,
 	#[error("Failed to count file paths in clipboard: {0}")]
 	PathCount(WindowsError),
 	#[error("Failed to get length of the path #{idx} in the clipboard: {err}")]
 	PathLength { idx: usize, err: WindowsError },
 	#[error("Failed to get file path #{idx} in the clipboard: {err}")]
 	FilePath { idx: usize, err: WindowsError },
+	#[error("HTML clipboard data doesn't say where its fragment is")]
+	InvalidHtml,
+	/// The position is a byte index into a string, or a UTF-16 code unit index into an OS string.
+	#[error("String has a NUL character at index {position}, which would cut it off there")]
+	InteriorNul { position: usize },
 	#[error("Failed to decode string as UTF-8: {0}")]
 	InvalidString(std::str::Utf8Error),
 	#[error("Failed to create dummy window: {0}")]
 	CreateWindow(WindowsError),
+	#[error("{0:#x} isn't a valid window handle")]
+	InvalidWindow(isize),
 	#[error("Failed to enumerate available clipboard formats: {0}")]
 	EnumClipboard(WindowsError),
+	#[error("Failed to register clipboard format {name:?}: {err}")]
+	RegisterFormat { name: String, err: WindowsError },
+	#[error("{0:?} isn't a known clipboard format")]
+	UnknownFormat(String),
+	#[error("Timed out waiting for the clipboard owner to provide the data")]
+	Timeout,
+	#[error("Failed to create clipboard mutex: {0}")]
+	CreateMutex(WindowsError),
+	#[error("Failed to wait for clipboard mutex: {0}")]
+	WaitMutex(WindowsError),
+	#[error("Failed to listen for clipboard changes: {0}")]
+	AddListener(WindowsError),
+	#[error("Failed to start a thread: {0}")]
+	SpawnThread(String),
+}
+
+impl Error {
+	/// Returns the underlying Windows error, if this error was caused by one.
+	pub fn windows_error(&self) -> Option<&WindowsError> {
+		match self {
+			Self::Allocation(err)
+			| Self::Locking(err)
+			| Self::InvalidObject(err)
+			| Self::OpenClipboard(err)
+			| Self::GetClipboard { err, .. }
+			| Self::SetClipboard { err, .. }
+			| Self::EmptyClipboard(err)
+			| Self::BitmapObject(err)
+			| Self::ReadMetafile(err)
+			| Self::CreateMetafile(err)
+			| Self::PathCount(err)
+			| Self::PathLength { err, .. }
+			| Self::FilePath { err, .. }
+			| Self::CreateWindow(err)
+			| Self::EnumClipboard(err)
+			| Self::RegisterFormat { err, .. }
+			| Self::CreateMutex(err)
+			| Self::WaitMutex(err)
+			| Self::AddListener(err) => Some(err),
+			#[cfg(feature = "image")]
+			Self::ImageBits(err) => Some(err),
+			Self::ClipboardAlreadyOpen
+			| Self::AllocationOverflow { .. }
+			| Self::EmptyAllocation
+			| Self::AllocationTooSmall { .. }
+			| Self::PayloadTooLarge { .. }
+			| Self::UnknownFormat(_)
+			| Self::InvalidWindow(_)
+			| Self::Timeout
+			| Self::NoFiles
+			| Self::EmptyUrl
+			| Self::InvalidWave
+			| Self::InvalidHtml
+			| Self::InteriorNul { .. }
+			| Self::InvalidString(_)
+			| Self::SpawnThread(_) => None,
+			#[cfg(feature = "image")]
+			Self::InvalidImage
+			| Self::ImageExtension(_)
+			| Self::SaveImage(_)
+			| Self::OpenImage(_)
+			| Self::DecodeImage(_) => None,
+		}
+	}
 }
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::{
+	fmt::{self, Display},
+	ops::Deref,
+	sync::Arc,
+};
+
+/// An error from the `image` crate, shared so that [`Error`](super::Error) can still be cloned and compared.
+///
+/// Two of these are equal if they have the same message, as `image`'s errors can't be compared themselves.
+#[derive(Debug, Clone)]
+pub struct ImageError(Arc<::image::ImageError>);
+
+impl ImageError {
+	/// Returns the `image` crate's error.
+	pub fn get(&self) -> &::image::ImageError {
+		&self.0
+	}
+}
+
+impl From<::image::ImageError> for ImageError {
+	fn from(err: ::image::ImageError) -> Self {
+		Self(Arc::new(err))
+	}
+}
+
+/// Reading and writing image files fails with I/O errors too, which `image` has a variant for.
+impl From<std::io::Error> for ImageError {
+	fn from(err: std::io::Error) -> Self {
+		::image::ImageError::IoError(err).into()
+	}
+}
+
+impl Deref for ImageError {
+	type Target = ::image::ImageError;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl PartialEq for ImageError {
+	fn eq(&self, other: &Self) -> bool {
+		self.0.to_string() == other.0.to_string()
+	}
+}
+
+impl Display for ImageError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Display::fmt(&self.0, f)
+	}
+}
+
+/// This stands in for the `image` crate's error, so its source is that error's source.
+impl std::error::Error for ImageError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.0.source()
+	}
+}
@@ -1,19 +1,17 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use std::{
-	fmt::{self, Display},
-	mem::MaybeUninit,
-};
+use std::fmt::{self, Display};
 use windows::{
 	core::PWSTR,
 	Win32::{
-		Foundation::{GetLastError, WIN32_ERROR},
+		Foundation::{
+			GetLastError, ERROR_ACCESS_DENIED, ERROR_CLIPBOARD_NOT_OPEN, ERROR_INSUFFICIENT_BUFFER,
+			ERROR_INVALID_HANDLE, ERROR_NOT_ENOUGH_MEMORY, ERROR_OUTOFMEMORY, WIN32_ERROR,
+		},
 		System::{
 			Diagnostics::Debug::{
-				FormatMessageW, FORMAT_MESSAGE_ALLOCATE_BUFFER, FORMAT_MESSAGE_FROM_SYSTEM,
-				FORMAT_MESSAGE_IGNORE_INSERTS,
+				FormatMessageW, FORMAT_MESSAGE_FROM_SYSTEM, FORMAT_MESSAGE_IGNORE_INSERTS,
 			},
-			Memory::{LocalFree, LocalSize},
 			SystemServices::{LANG_NEUTRAL, SUBLANG_DEFAULT},
 		},
 	},
@@ -38,50 +36,104 @@ impl WindowsError {
 			None
 		}
 	}
+
+	/// Creates an error from a raw Win32 error code.
+	pub const fn from_code(code: u32) -> Self {
+		Self(WIN32_ERROR(code))
+	}
+
+	/// Returns the raw Win32 error code.
+	pub const fn code(&self) -> u32 {
+		self.0 .0
+	}
+
+	/// Returns `true` if this is `ERROR_ACCESS_DENIED`.
+	///
+	/// This usually means another program currently has the clipboard open, and the operation can be retried.
+	pub const fn is_access_denied(&self) -> bool {
+		self.0 .0 == ERROR_ACCESS_DENIED.0
+	}
+
+	/// Returns `true` if this is `ERROR_NOT_ENOUGH_MEMORY` or `ERROR_OUTOFMEMORY`.
+	pub const fn is_out_of_memory(&self) -> bool {
+		self.0 .0 == ERROR_NOT_ENOUGH_MEMORY.0 || self.0 .0 == ERROR_OUTOFMEMORY.0
+	}
+
+	/// Returns `true` if this is `ERROR_INVALID_HANDLE`.
+	pub const fn is_invalid_handle(&self) -> bool {
+		self.0 .0 == ERROR_INVALID_HANDLE.0
+	}
+
+	/// Returns `true` if this is `ERROR_CLIPBOARD_NOT_OPEN`.
+	pub const fn is_clipboard_not_open(&self) -> bool {
+		self.0 .0 == ERROR_CLIPBOARD_NOT_OPEN.0
+	}
+}
+
+impl From<WindowsError> for u32 {
+	fn from(err: WindowsError) -> Self {
+		err.code()
+	}
 }
 
 const fn make_lang_id(lang: u32, sublang: u32) -> u32 {
 	lang | (sublang << 10)
 }
 
-impl std::error::Error for WindowsError {}
+/// How many UTF-16 code units of a system message are made room for at first.
+/// Almost every message fits, but the buffer grows for any that don't.
+const MESSAGE_BUFFER_LEN: usize = 256;
 
-impl Display for WindowsError {
-	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		if self.0.is_ok() {
-			return write!(f, "OK (no error)");
-		}
-		// Create an uninitialized pointer.
-		// Windows will later replace this with a pointer to our error message.
-		let mut err_ptr = MaybeUninit::<*mut u16>::uninit();
-		// Format our error message, using FormatMessageA.
-		if unsafe {
+/// FormatMessageW can't write messages any longer than this.
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+/// Looks up the system's message for an error code, or returns `None` if there isn't one.
+fn system_message(code: u32) -> Option<Vec<u16>> {
+	let mut buffer = vec![0_u16; MESSAGE_BUFFER_LEN];
+	loop {
+		// This returns the number of UTF-16 code units written, excluding the null terminator.
+		let len = unsafe {
 			FormatMessageW(
-				FORMAT_MESSAGE_ALLOCATE_BUFFER
-					| FORMAT_MESSAGE_FROM_SYSTEM
-					| FORMAT_MESSAGE_IGNORE_INSERTS,
+				FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
 				std::ptr::null(),
-				self.0 .0,
+				code,
 				make_lang_id(LANG_NEUTRAL, SUBLANG_DEFAULT),
-				PWSTR(err_ptr.as_mut_ptr() as _),
-				0,
+				PWSTR(buffer.as_mut_ptr()),
+				buffer.len() as u32,
 				std::ptr::null_mut(),
 			)
-		} == 0
+		} as usize;
+		if len != 0 {
+			buffer.truncate(len);
+			return Some(buffer);
+		}
+		if unsafe { GetLastError() } != ERROR_INSUFFICIENT_BUFFER || buffer.len() >= MAX_MESSAGE_LEN
 		{
-			return Err(fmt::Error);
+			return None;
+		}
+		buffer.resize(buffer.len() * 2, 0);
+	}
+}
+
+impl std::error::Error for WindowsError {}
+
+impl Display for WindowsError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.0.is_ok() {
+			return write!(f, "OK (no error)");
 		}
-		let err_ptr = unsafe { err_ptr.assume_init() };
-		// Ensure that the error message buffer is always freed, no matter what happens.
-		// A defer will run when the function exits, or even if it panics!
-		scopeguard::defer! { unsafe { LocalFree(err_ptr as isize); } };
-		// Get the length of the u16 buffer.
-		let len = unsafe { LocalSize(err_ptr as isize) } / std::mem::size_of::<u16>() - 1;
-		let u16_slice = unsafe { std::slice::from_raw_parts(err_ptr, len) };
+		// Windows doesn't have a message for every error code, so fall back to just printing the code.
+		let message = match system_message(self.0 .0) {
+			Some(message) => message,
+			None => return write!(f, "Windows error {:#010x}", self.0 .0),
+		};
+		// System messages end with a line break, which we don't want in the middle of an error chain.
 		write!(
 			f,
 			"{}",
-			Wtf8Buf::from_ill_formed_utf16(u16_slice).to_string_lossy()
+			Wtf8Buf::from_ill_formed_utf16(&message)
+				.to_string_lossy()
+				.trim_end()
 		)
 	}
 }
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+	error::{Error, Result, WindowsError},
+	snapshot::ClipboardSnapshot,
+	ClipboardHandle,
+};
+use std::{marker::PhantomData, mem::ManuallyDrop, ops::Deref};
+use windows::{
+	core::PCWSTR,
+	Win32::{
+		Foundation::{CloseHandle, BOOL, HANDLE, WAIT_FAILED},
+		System::Threading::{CreateMutexW, ReleaseMutex, WaitForSingleObject},
+	},
+};
+
+/// Tells `WaitForSingleObject` to wait for as long as it takes.
+const INFINITE: u32 = u32::MAX;
+
+/// A clipboard handle that has exclusive use of the clipboard until it's dropped.
+///
+/// The system clipboard is shared by every thread and every process,
+/// so tests that write to it will clobber each other when run in parallel.
+/// Acquiring this at the top of a test serializes clipboard access across threads and processes,
+/// by waiting on a named mutex.
+///
+/// The mutex is owned by the thread that acquired it, so this can't be sent to another thread.
+pub struct ExclusiveClipboard {
+	/// Dropped by hand, as it has to be gone before the mutex is released.
+	handle: ManuallyDrop<ClipboardHandle>,
+	mutex: HANDLE,
+	snapshot: Option<ClipboardSnapshot>,
+	_not_send: PhantomData<*const ()>,
+}
+
+impl ExclusiveClipboard {
+	/// The name of the mutex that serializes clipboard access.
+	pub const MUTEX_NAME: &'static str = "clipee-test-lock";
+
+	/// Waits until no one else has exclusive use of the clipboard, then takes it.
+	pub fn acquire() -> Result<Self> {
+		let mutex = lock_mutex()?;
+		let handle = match ClipboardHandle::new() {
+			Ok(handle) => handle,
+			Err(err) => {
+				unsafe { release_mutex(mutex) };
+				return Err(err);
+			}
+		};
+		Ok(Self {
+			handle: ManuallyDrop::new(handle),
+			mutex,
+			snapshot: None,
+			_not_send: PhantomData,
+		})
+	}
+
+	/// Like [`ExclusiveClipboard::acquire`], but also takes a snapshot of the clipboard contents,
+	/// which are put back on the clipboard when this is dropped.
+	///
+	/// See [`ClipboardSnapshot`] for what can and can't be restored.
+	pub fn acquire_with_snapshot() -> Result<Self> {
+		let mut exclusive = Self::acquire()?;
+		exclusive.snapshot = Some(exclusive.handle.snapshot()?);
+		Ok(exclusive)
+	}
+
+	/// Refreshes the clipboard handle, as with [`ClipboardHandle::refresh`],
+	/// so that the snapshot can still be restored once it's no longer valid.
+	pub fn refresh(&mut self) -> Result<()> {
+		self.handle.refresh()
+	}
+}
+
+impl Deref for ExclusiveClipboard {
+	type Target = ClipboardHandle;
+
+	fn deref(&self) -> &Self::Target {
+		&self.handle
+	}
+}
+
+impl Drop for ExclusiveClipboard {
+	fn drop(&mut self) {
+		if let Some(snapshot) = self.snapshot.take() {
+			// There's nothing useful we can do about a failed restore while dropping.
+			let _ = self.handle.restore_snapshot(&snapshot);
+		}
+		// Whoever takes the mutex next will open the clipboard,
+		// which fails for as long as this handle still has it open.
+		unsafe {
+			ManuallyDrop::drop(&mut self.handle);
+			release_mutex(self.mutex);
+		}
+	}
+}
+
+/// Opens the named mutex, creating it if needed, and waits until we own it.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+fn lock_mutex() -> Result<HANDLE> {
+	let wide_name = ExclusiveClipboard::MUTEX_NAME
+		.encode_utf16()
+		.chain(std::iter::once(0))
+		.collect::<Vec<u16>>();
+	let mutex = unsafe {
+		CreateMutexW(
+			std::ptr::null(),
+			BOOL::from(false),
+			PCWSTR(wide_name.as_ptr()),
+		)
+	};
+	if mutex.is_invalid() {
+		return Err(Error::CreateMutex(WindowsError::from_last_error()));
+	}
+	// WAIT_ABANDONED means whoever held it before exited without releasing it,
+	// which still leaves us as the owner.
+	if unsafe { WaitForSingleObject(mutex, INFINITE) } == WAIT_FAILED.0 {
+		let err = WindowsError::from_last_error();
+		unsafe { CloseHandle(mutex) };
+		return Err(Error::WaitMutex(err));
+	}
+	Ok(mutex)
+}
+
+/// Releases and closes a mutex we own.
+unsafe fn release_mutex(mutex: HANDLE) {
+	ReleaseMutex(mutex);
+	CloseHandle(mutex);
+}
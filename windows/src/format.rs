@@ -1,14 +1,40 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+#[cfg(feature = "arboard-compat")]
+pub mod arboard_compat;
+#[cfg(feature = "image")]
 pub mod bitmap;
 pub mod files;
+pub mod html;
+#[cfg(feature = "image")]
+pub(crate) mod image_file;
+pub mod metafile;
+pub mod png;
+pub mod registered;
 pub mod string;
+pub mod wave;
 
-use windows::Win32::System::SystemServices::{
-	CF_BITMAP, CF_DIB, CF_DIBV5, CF_HDROP, CF_TEXT, CF_UNICODETEXT, CLIPBOARD_FORMATS,
+use crate::{
+	error::{Error, Result, WindowsError},
+	ClipboardHandleInner,
+};
+use std::{
+	fmt::{self, Display},
+	marker::PhantomData,
+	str::FromStr,
+};
+use windows::{
+	core::PCWSTR,
+	Win32::System::{
+		DataExchange::{EnumClipboardFormats, GetClipboardFormatNameW, RegisterClipboardFormatW},
+		SystemServices::{
+			CF_BITMAP, CF_DIB, CF_DIBV5, CF_ENHMETAFILE, CF_HDROP, CF_TEXT, CF_UNICODETEXT,
+			CF_WAVE, CLIPBOARD_FORMATS,
+		},
+	},
 };
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ClipboardFormat {
 	Text,
 	Bitmap,
@@ -16,10 +42,46 @@ pub enum ClipboardFormat {
 	BitmapV5,
 	DropHandle,
 	UnicodeText,
+	EnhMetafile,
+	Wave,
+	/// A format registered at runtime with `RegisterClipboardFormatW`.
+	Registered(u32),
 }
 
+/// The canonical names of the standard formats, as used by [`Display`] and [`FromStr`].
+const STANDARD_NAMES: &[(ClipboardFormat, &str)] = &[
+	(ClipboardFormat::Text, "CF_TEXT"),
+	(ClipboardFormat::Bitmap, "CF_BITMAP"),
+	(ClipboardFormat::BitmapInfo, "CF_DIB"),
+	(ClipboardFormat::BitmapV5, "CF_DIBV5"),
+	(ClipboardFormat::DropHandle, "CF_HDROP"),
+	(ClipboardFormat::UnicodeText, "CF_UNICODETEXT"),
+	(ClipboardFormat::EnhMetafile, "CF_ENHMETAFILE"),
+	(ClipboardFormat::Wave, "CF_WAVE"),
+];
+
 impl ClipboardFormat {
+	/// Registers a clipboard format by name, or gets the existing format if it's already been registered.
+	pub fn register(name: &str) -> Result<Self> {
+		let wide_name = name
+			.encode_utf16()
+			.chain(std::iter::once(0))
+			.collect::<Vec<u16>>();
+		let format = unsafe { RegisterClipboardFormatW(PCWSTR(wide_name.as_ptr())) };
+		if format == 0 {
+			return Err(Error::RegisterFormat {
+				name: name.to_owned(),
+				err: WindowsError::from_last_error(),
+			});
+		}
+		Ok(Self::Registered(format))
+	}
+
 	pub fn try_from_u32(format: u32) -> Option<Self> {
+		// Registered formats are always in this range.
+		if (0xC000..=0xFFFF).contains(&format) {
+			return Some(Self::Registered(format));
+		}
 		match CLIPBOARD_FORMATS(format) {
 			CF_TEXT => Some(Self::Text),
 			CF_BITMAP => Some(Self::Bitmap),
@@ -27,11 +89,70 @@ impl ClipboardFormat {
 			CF_DIBV5 => Some(Self::BitmapV5),
 			CF_HDROP => Some(Self::DropHandle),
 			CF_UNICODETEXT => Some(Self::UnicodeText),
+			CF_ENHMETAFILE => Some(Self::EnhMetafile),
+			CF_WAVE => Some(Self::Wave),
 			_ => None,
 		}
 	}
 }
 
+/// Standard formats are shown by their `CF_` constant, and registered formats by their registered name,
+/// or as `#` followed by their ID if the name can't be looked up.
+impl Display for ClipboardFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let id = match self {
+			Self::Registered(id) => *id,
+			standard => {
+				let (_, name) = STANDARD_NAMES
+					.iter()
+					.find(|(format, _)| format == standard)
+					.expect("every standard format has a name");
+				return f.write_str(name);
+			}
+		};
+		// Registered format names can't be longer than this.
+		let mut name = [0_u16; 256];
+		let len = unsafe { GetClipboardFormatNameW(id, &mut name) };
+		if len <= 0 {
+			return write!(f, "#{}", id);
+		}
+		f.write_str(&String::from_utf16_lossy(&name[..len as usize]))
+	}
+}
+
+/// Parses a format from its `CF_` constant, a `#` followed by its ID, a friendly alias
+/// (`text`, `unicode`, `ansi`, `files`, `bitmap`, `dib`, `emf`, `wave`, `html`, or `png`),
+/// or otherwise a registered format name, which is registered if it hasn't been already.
+///
+/// All of these except registered format names are case-insensitive.
+impl FromStr for ClipboardFormat {
+	type Err = Error;
+
+	fn from_str(name: &str) -> Result<Self> {
+		if let Some((format, _)) = STANDARD_NAMES
+			.iter()
+			.find(|(_, canonical)| canonical.eq_ignore_ascii_case(name))
+		{
+			return Ok(*format);
+		}
+		if let Some(id) = name.strip_prefix('#').and_then(|id| id.parse().ok()) {
+			return Self::try_from_u32(id).ok_or_else(|| Error::UnknownFormat(name.to_owned()));
+		}
+		match name.to_ascii_lowercase().as_str() {
+			"text" | "unicode" => Ok(Self::UnicodeText),
+			"ansi" => Ok(Self::Text),
+			"files" => Ok(Self::DropHandle),
+			"bitmap" => Ok(Self::Bitmap),
+			"dib" => Ok(Self::BitmapInfo),
+			"emf" => Ok(Self::EnhMetafile),
+			"wave" => Ok(Self::Wave),
+			"html" => Self::register(registered::HTML),
+			"png" => Self::register(registered::PNG),
+			_ => Self::register(name),
+		}
+	}
+}
+
 impl From<ClipboardFormat> for CLIPBOARD_FORMATS {
 	fn from(format: ClipboardFormat) -> Self {
 		match format {
@@ -41,6 +162,9 @@ impl From<ClipboardFormat> for CLIPBOARD_FORMATS {
 			ClipboardFormat::BitmapV5 => CF_DIBV5,
 			ClipboardFormat::DropHandle => CF_HDROP,
 			ClipboardFormat::UnicodeText => CF_UNICODETEXT,
+			ClipboardFormat::EnhMetafile => CF_ENHMETAFILE,
+			ClipboardFormat::Wave => CF_WAVE,
+			ClipboardFormat::Registered(format) => CLIPBOARD_FORMATS(format),
 		}
 	}
 }
@@ -50,3 +174,51 @@ impl From<ClipboardFormat> for u32 {
 		CLIPBOARD_FORMATS::from(format).0
 	}
 }
+
+/// A format that's on the clipboard, including ones that don't have a [`ClipboardFormat`] variant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FormatInfo {
+	/// The raw format ID.
+	pub id: u32,
+	/// The format, if it's one we know about.
+	pub format: Option<ClipboardFormat>,
+}
+
+/// Lazily enumerates the formats on the clipboard, with `EnumClipboardFormats`.
+///
+/// This borrows the clipboard handle, as enumeration only works while the clipboard is open.
+pub(crate) struct FormatIter<'handle> {
+	last_format: u32,
+	done: bool,
+	_handle: PhantomData<&'handle ClipboardHandleInner>,
+}
+
+impl<'handle> FormatIter<'handle> {
+	pub(crate) fn new(_handle: &'handle ClipboardHandleInner) -> Self {
+		Self {
+			last_format: 0,
+			done: false,
+			_handle: PhantomData,
+		}
+	}
+}
+
+impl Iterator for FormatIter<'_> {
+	type Item = Result<FormatInfo>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		// Each call only depends on the format passed in, so stopping early doesn't leave anything behind.
+		self.last_format = unsafe { EnumClipboardFormats(self.last_format) };
+		if self.last_format == 0 {
+			self.done = true;
+			return WindowsError::try_from_last_error().map(|err| Err(Error::EnumClipboard(err)));
+		}
+		Some(Ok(FormatInfo {
+			id: self.last_format,
+			format: ClipboardFormat::try_from_u32(self.last_format),
+		}))
+	}
+}
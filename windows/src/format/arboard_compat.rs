@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Conversions to and from [`arboard::ImageData`], for code that's moving over from `arboard`.
+//!
+//! These are functions rather than `From` implementations, as neither type is ours.
+//! Both are RGBA with 8 bits per channel, top row first, so the pixels never have to be rearranged.
+
+use crate::error::{Error, Result};
+use arboard::ImageData;
+use image::{RgbImage, RgbaImage};
+use std::borrow::Cow;
+
+/// Converts an image to `arboard`'s representation, without copying its pixels.
+pub fn to_image_data(image: RgbaImage) -> ImageData<'static> {
+	ImageData {
+		width: image.width() as usize,
+		height: image.height() as usize,
+		bytes: Cow::Owned(image.into_raw()),
+	}
+}
+
+/// Converts an image without an alpha channel to `arboard`'s representation, making it fully opaque.
+pub fn rgb_to_image_data(image: &RgbImage) -> ImageData<'static> {
+	to_image_data(image::DynamicImage::ImageRgb8(image.clone()).into_rgba8())
+}
+
+/// Converts an image from `arboard`'s representation, only copying the pixels if they're borrowed.
+///
+/// Fails with [`Error::InvalidImage`] if there aren't exactly `width * height * 4` bytes of pixels,
+/// or the image is too large for the `image` crate.
+pub fn from_image_data(data: ImageData<'_>) -> Result<RgbaImage> {
+	let width = u32::try_from(data.width).map_err(|_| Error::InvalidImage)?;
+	let height = u32::try_from(data.height).map_err(|_| Error::InvalidImage)?;
+	let len = data
+		.width
+		.checked_mul(data.height)
+		.and_then(|pixels| pixels.checked_mul(4))
+		.ok_or(Error::InvalidImage)?;
+	if data.bytes.len() != len {
+		return Err(Error::InvalidImage);
+	}
+	RgbaImage::from_raw(width, height, data.bytes.into_owned()).ok_or(Error::InvalidImage)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn test_image() -> RgbaImage {
+		RgbaImage::from_fn(3, 2, |x, y| {
+			image::Rgba([x as u8 * 80, y as u8 * 200, 40, 255 - x as u8 * 50])
+		})
+	}
+
+	#[test]
+	fn round_trip() {
+		let image = test_image();
+		let data = to_image_data(image.clone());
+		assert_eq!((data.width, data.height), (3, 2));
+		// The top-left pixel comes first, red channel first.
+		assert_eq!(data.bytes[..4], [0, 0, 40, 255]);
+		assert_eq!(
+			from_image_data(data).expect("failed to convert image"),
+			image
+		);
+	}
+
+	#[test]
+	fn owned_pixels_are_moved() {
+		let image = test_image();
+		let pixels = image.as_ptr();
+		let data = to_image_data(image);
+		assert!(matches!(data.bytes, Cow::Owned(_)));
+		assert_eq!(data.bytes.as_ptr(), pixels);
+		let image = from_image_data(data).expect("failed to convert image");
+		assert_eq!(image.as_ptr(), pixels);
+	}
+
+	#[test]
+	fn borrowed_pixels() {
+		let image = test_image();
+		let data = ImageData {
+			width: 3,
+			height: 2,
+			bytes: Cow::Borrowed(image.as_raw()),
+		};
+		assert_eq!(
+			from_image_data(data).expect("failed to convert image"),
+			image
+		);
+	}
+
+	#[test]
+	fn rgb_is_opaque() {
+		let image = RgbImage::from_pixel(2, 2, image::Rgb([1, 2, 3]));
+		let data = rgb_to_image_data(&image);
+		assert_eq!(data.bytes.as_ref(), [1, 2, 3, 255].repeat(4));
+	}
+
+	#[test]
+	fn wrong_length() {
+		let data = ImageData {
+			width: 3,
+			height: 2,
+			bytes: Cow::Owned(vec![0; 3 * 2 * 4 - 1]),
+		};
+		assert_eq!(from_image_data(data), Err(Error::InvalidImage));
+	}
+}
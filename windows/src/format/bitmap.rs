@@ -2,56 +2,819 @@
 
 use crate::{
 	error::{Error, Result, WindowsError},
-	lock::LockedPtr,
+	lock::{self, LockedPtr},
 };
-use image::RgbImage;
+use image::{RgbImage, RgbaImage};
 use windows::Win32::{
 	Foundation::HWND,
-	Graphics::Gdi::{GetDC, GetDIBits, BITMAPINFO, DIB_RGB_COLORS, HBITMAP},
+	Graphics::Gdi::{
+		GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO, BITMAPINFOHEADER,
+		BITMAPV5HEADER, BI_BITFIELDS, BI_RGB, DIB_RGB_COLORS, HBITMAP, LCS_GM_IMAGES,
+	},
 };
 
-pub fn get(hbitmap: HBITMAP, bitmap_info: LockedPtr<BITMAPINFO>) -> Result<RgbImage> {
-	let bitmap_header = unsafe { &(*bitmap_info.as_ptr()).bmiHeader };
-	// this is just weird.
-	let should_flip = bitmap_header.biHeight.is_positive();
-	// Get the size of the bitmap.
-	let width = bitmap_header.biWidth;
-	let height = bitmap_header.biHeight.abs() as u32;
-	let bits_per_pixel = bitmap_header.biBitCount;
-	let bytes_per_pixel = bits_per_pixel / 8;
-	let size = bitmap_header.biSizeImage as usize;
+/// `LCS_sRGB`, which the windows crate doesn't expose.
+const LCS_SRGB: u32 = u32::from_be_bytes(*b"sRGB");
+
+pub fn get(
+	hbitmap: HBITMAP,
+	bitmap_info: LockedPtr<BITMAPINFO>,
+	max_size: Option<usize>,
+) -> Result<RgbImage> {
+	let header = unsafe { (*bitmap_info.as_ptr()).bmiHeader };
+	let dib = bitmap_info.as_bytes()?;
+	check_dib_size(dib, &header)?;
+	// GetDIBits writes the masks or palette of the pixels it hands back after the header,
+	// which isn't ours to write to in the clipboard's memory, so it gets a copy of its own.
+	let mut info = info_copy(dib, &header);
+	let pixels = get_bits(hbitmap, info.as_mut_ptr() as *mut BITMAPINFO, max_size)?;
+	let info = unsafe { std::slice::from_raw_parts(info.as_ptr() as *const u8, info.len() * 4) };
+	decode(pixels, &header, channel_masks(info, &header)?)
+}
+
+/// Copies the header of a DIB, along with room for the largest color table that GetDIBits writes after it,
+/// which is a palette of 256 colors.
+///
+/// The copy is made of `u32`s, as GetDIBits expects the header to be aligned like one.
+fn info_copy(dib: &[u8], header: &BITMAPINFOHEADER) -> Vec<u32> {
+	let len = (header.biSize as usize).saturating_add(256 * 4);
+	let mut info = vec![0_u32; len.div_ceil(4)];
+	let src = &dib[..len.min(dib.len())];
+	unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), info.as_mut_ptr() as *mut u8, src.len()) };
+	info
+}
+
+/// Gets the pixels of a bitmap that doesn't have an accompanying `CF_DIB`, as 24-bit RGB.
+pub fn get_without_info(hbitmap: HBITMAP, max_size: Option<usize>) -> Result<RgbImage> {
+	let mut bitmap = BITMAP::default();
+	if unsafe {
+		GetObjectW(
+			hbitmap,
+			std::mem::size_of::<BITMAP>() as i32,
+			&mut bitmap as *mut BITMAP as *mut _,
+		)
+	} == 0
+	{
+		return Err(Error::BitmapObject(WindowsError::from_last_error()));
+	}
+	let mut header = BITMAPINFOHEADER {
+		biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+		biWidth: bitmap.bmWidth,
+		biHeight: bitmap.bmHeight,
+		biPlanes: 1,
+		biBitCount: 24,
+		biCompression: BI_RGB as u32,
+		..Default::default()
+	};
+	header.biSizeImage = u32::try_from(image_size(&header)?).map_err(|_| Error::InvalidImage)?;
+	let mut bitmap_info = BITMAPINFO {
+		bmiHeader: header,
+		..Default::default()
+	};
+	let pixels = get_bits(hbitmap, &mut bitmap_info, max_size)?;
+	decode(pixels, &header, None)
+}
+
+/// Copies the pixels out of a bitmap, in the format described by `bitmap_info`,
+/// which GetDIBits writes its color table back into.
+fn get_bits(
+	hbitmap: HBITMAP,
+	bitmap_info: *mut BITMAPINFO,
+	max_size: Option<usize>,
+) -> Result<Vec<u8>> {
+	// GetDIBits writes to the header, so take a copy of it first.
+	let bitmap_header = unsafe { (*bitmap_info).bmiHeader };
+	// GetDIBits takes the number of rows, which is the height without its sign.
+	let height = bitmap_header.biHeight.unsigned_abs();
+	let size = image_size(&bitmap_header)?;
+	if let Some(limit) = max_size {
+		if size > limit {
+			return Err(Error::PayloadTooLarge { size, limit });
+		}
+	}
 	// Alright, create a Vec with all the pixels, and then copy them into the Vec.
-	let mut raw = vec![0_u8; size];
+	let mut pixels = vec![0_u8; size];
 	let gdc = unsafe { GetDC(HWND::default()) };
+	scopeguard::defer! { unsafe { ReleaseDC(HWND::default(), gdc); } };
 	if unsafe {
 		GetDIBits(
 			gdc,
 			hbitmap,
 			0,
-			height as u32,
-			raw.as_mut_ptr() as *mut _,
-			bitmap_info.as_mut_ptr(),
+			height,
+			pixels.as_mut_ptr() as *mut _,
+			bitmap_info,
 			DIB_RGB_COLORS,
 		)
 	} == 0
 	{
 		return Err(Error::ImageBits(WindowsError::from_last_error()));
 	}
+	Ok(pixels)
+}
+
+/// Turns the pixels of a DIB, laid out as described by its header, into a correctly oriented RGB image.
+fn decode(
+	mut pixels: Vec<u8>,
+	header: &BITMAPINFOHEADER,
+	masks: Option<ChannelMasks>,
+) -> Result<RgbImage> {
+	let width = header.biWidth.unsigned_abs();
+	let height = header.biHeight.unsigned_abs();
+	let bytes_per_pixel = usize::from(header.biBitCount / 8);
 	// Lop off padding.
-	let row_byte_length = ((i32::from(bits_per_pixel) * width + 31) / 32 * 4) as usize;
-	let mut pixels = vec![0; height as usize * row_byte_length];
-	for (raw_chunk, dst_chunk) in raw
-		.chunks_exact(row_byte_length)
-		.zip(pixels.chunks_exact_mut(width as usize * bytes_per_pixel as usize))
+	compact_rows(
+		&mut pixels,
+		width as usize * bytes_per_pixel,
+		stride(header.biBitCount, width)?,
+		height as usize,
+	);
+	let pixels = match masks {
+		Some(masks) => masked_to_rgb(&pixels, bytes_per_pixel, masks),
+		None => {
+			// DIBs store their pixels as BGR(X), so shuffle them into RGB.
+			bgr_to_rgb(&mut pixels, bytes_per_pixel)?;
+			pixels
+		}
+	};
+	let mut image = RgbImage::from_raw(width, height, pixels).ok_or(Error::InvalidImage)?;
+	// A positive height means the rows are stored bottom-up, so the first row is the bottom of the image.
+	// A negative height means they're already top-down.
+	if header.biHeight.is_positive() {
+		image::imageops::flip_vertical_in_place(&mut image);
+	}
+	Ok(image)
+}
+
+/// Returns the number of bytes that the pixels of an uncompressed DIB take up, padding included.
+///
+/// This is worked out from the dimensions, as `biSizeImage` is allowed to be zero for uncompressed DIBs,
+/// and it's what GetDIBits will write regardless.
+fn image_size(header: &BITMAPINFOHEADER) -> Result<usize> {
+	stride(header.biBitCount, header.biWidth.unsigned_abs())?
+		.checked_mul(header.biHeight.unsigned_abs() as usize)
+		.ok_or(Error::InvalidImage)
+}
+
+/// Returns the number of bytes between the header of a DIB and its pixels,
+/// which hold the channel masks or the palette, if it has either.
+fn color_table_size(header: &BITMAPINFOHEADER) -> usize {
+	// The masks are part of the V4 and V5 headers, and only follow a BITMAPINFOHEADER.
+	let masks = if header.biCompression as i32 == BI_BITFIELDS
+		&& header.biSize as usize == std::mem::size_of::<BITMAPINFOHEADER>()
 	{
-		dst_chunk.copy_from_slice(&raw_chunk[..width as usize * bytes_per_pixel as usize]);
+		12
+	} else {
+		0
+	};
+	// Palettized DIBs have every color unless they say otherwise; the others can still have a palette as a hint.
+	let colors = match header.biClrUsed {
+		0 if (1..=8).contains(&header.biBitCount) => 1 << header.biBitCount,
+		colors => colors as usize,
+	};
+	colors.saturating_mul(4).saturating_add(masks)
+}
+
+/// Makes sure that a DIB is big enough to hold the header, color table and pixels that its header claims it has.
+///
+/// The pixels of an uncompressed DIB take up as much as its dimensions say, even if `biSizeImage` is zero,
+/// as it's allowed to be for those.
+fn check_dib_size(dib: &[u8], header: &BITMAPINFOHEADER) -> Result<()> {
+	if (header.biSize as usize) < std::mem::size_of::<BITMAPINFOHEADER>() {
+		return Err(Error::InvalidImage);
 	}
-	// Trim off excess length
-	pixels.truncate(width as usize * height as usize * bytes_per_pixel as usize);
-	let mut image =
-		RgbImage::from_raw(width as u32, height as u32, pixels).ok_or(Error::InvalidImage)?;
-	if should_flip {
-		image::imageops::flip_vertical_in_place(&mut image);
+	let pixels = match header.biCompression as i32 {
+		BI_RGB | BI_BITFIELDS => image_size(header)?.max(header.biSizeImage as usize),
+		_ => header.biSizeImage as usize,
+	};
+	let needed = (header.biSize as usize)
+		.checked_add(color_table_size(header))
+		.and_then(|len| len.checked_add(pixels))
+		.ok_or(Error::InvalidImage)?;
+	if dib.len() < needed {
+		return Err(Error::AllocationTooSmall {
+			needed,
+			actual: dib.len(),
+		});
+	}
+	Ok(())
+}
+
+/// Decodes a `CF_DIBV5`, keeping its alpha channel.
+pub fn get_rgba(dib: &LockedPtr<u8>) -> Result<RgbaImage> {
+	rgba_from_dibv5(dib.as_bytes()?)
+}
+
+fn rgba_from_dibv5(bytes: &[u8]) -> Result<RgbaImage> {
+	let header_size = std::mem::size_of::<BITMAPV5HEADER>();
+	if bytes.len() < header_size {
+		return Err(Error::AllocationTooSmall {
+			needed: header_size,
+			actual: bytes.len(),
+		});
+	}
+	let header = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const BITMAPV5HEADER) };
+	let width = header.bV5Width.unsigned_abs();
+	let height = header.bV5Height.unsigned_abs();
+	// A positive height means the rows are stored bottom-up.
+	let bottom_up = header.bV5Height.is_positive();
+	let bytes_per_pixel = match header.bV5BitCount {
+		24 => 3,
+		32 => 4,
+		_ => return Err(Error::InvalidImage),
+	};
+	let masks = match header.bV5Compression as i32 {
+		BI_BITFIELDS => ChannelMasks {
+			red: header.bV5RedMask,
+			green: header.bV5GreenMask,
+			blue: header.bV5BlueMask,
+			alpha: header.bV5AlphaMask,
+		},
+		BI_RGB if bytes_per_pixel == 4 => ChannelMasks {
+			alpha: header.bV5AlphaMask,
+			..ChannelMasks::BGR
+		},
+		BI_RGB => ChannelMasks::BGR,
+		_ => return Err(Error::InvalidImage),
+	};
+	let stride = stride(header.bV5BitCount, width)?;
+	let offset = (header.bV5ClrUsed as usize)
+		.checked_mul(4)
+		.and_then(|colors| colors.checked_add(header.bV5Size as usize))
+		.ok_or(Error::InvalidImage)?;
+	let pixels_len = stride
+		.checked_mul(height as usize)
+		.ok_or(Error::InvalidImage)?;
+	let pixel_data = bytes
+		.get(offset..)
+		.filter(|data| data.len() >= pixels_len)
+		.ok_or(Error::InvalidImage)?;
+	let mut image = RgbaImage::new(width, height);
+	for (y, row) in image.rows_mut().enumerate() {
+		let src_y = if bottom_up {
+			height as usize - 1 - y
+		} else {
+			y
+		};
+		let src_row = &pixel_data[src_y * stride..][..width as usize * bytes_per_pixel];
+		for (pixel, src) in row.zip(src_row.chunks_exact(bytes_per_pixel)) {
+			let mut value = [0_u8; 4];
+			value[..bytes_per_pixel].copy_from_slice(src);
+			pixel.0 = masks.decode(u32::from_le_bytes(value));
+		}
 	}
 	Ok(image)
 }
+
+/// The bits of a pixel that make up each of its channels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct ChannelMasks {
+	red: u32,
+	green: u32,
+	blue: u32,
+	alpha: u32,
+}
+
+impl ChannelMasks {
+	/// The standard layout of an uncompressed DIB, with no alpha channel.
+	const BGR: Self = Self {
+		red: 0x00FF_0000,
+		green: 0x0000_FF00,
+		blue: 0x0000_00FF,
+		alpha: 0,
+	};
+
+	/// The layout of a 16-bit DIB without masks, with 5 bits per channel.
+	const RGB555: Self = Self {
+		red: 0x7C00,
+		green: 0x03E0,
+		blue: 0x001F,
+		alpha: 0,
+	};
+
+	/// Decodes a pixel into 8-bit RGBA. If there's no alpha channel, the pixel is fully opaque.
+	fn decode(&self, pixel: u32) -> [u8; 4] {
+		let alpha = match self.alpha {
+			0 => u8::MAX,
+			mask => Self::channel(pixel, mask),
+		};
+		[
+			Self::channel(pixel, self.red),
+			Self::channel(pixel, self.green),
+			Self::channel(pixel, self.blue),
+			alpha,
+		]
+	}
+
+	/// Extracts a single channel from a pixel, scaling it to 8 bits.
+	fn channel(pixel: u32, mask: u32) -> u8 {
+		if mask == 0 {
+			return 0;
+		}
+		let shift = mask.trailing_zeros();
+		let max = u64::from(mask >> shift);
+		let value = u64::from((pixel & mask) >> shift);
+		((value * 255 + max / 2) / max) as u8
+	}
+}
+
+/// Works out how the channels of a DIB's pixels are laid out, if they aren't plain BGR(X).
+fn channel_masks(dib: &[u8], header: &BITMAPINFOHEADER) -> Result<Option<ChannelMasks>> {
+	match (header.biBitCount, header.biCompression as i32) {
+		(16 | 32, BI_BITFIELDS) => read_masks(dib).map(Some),
+		(16, _) => Ok(Some(ChannelMasks::RGB555)),
+		// Uncompressed 32-bit DIBs are BGRX, which is handled without masks.
+		_ => Ok(None),
+	}
+}
+
+/// Reads the red, green and blue masks of a `BI_BITFIELDS` DIB.
+fn read_masks(dib: &[u8]) -> Result<ChannelMasks> {
+	// The masks follow a BITMAPINFOHEADER, which is also where they are in the V4 and V5 headers.
+	let offset = std::mem::size_of::<BITMAPINFOHEADER>();
+	let masks = dib.get(offset..offset + 12).ok_or(Error::InvalidImage)?;
+	let mask = |idx: usize| {
+		u32::from_le_bytes([
+			masks[idx * 4],
+			masks[idx * 4 + 1],
+			masks[idx * 4 + 2],
+			masks[idx * 4 + 3],
+		])
+	};
+	Ok(ChannelMasks {
+		red: mask(0),
+		green: mask(1),
+		blue: mask(2),
+		alpha: 0,
+	})
+}
+
+/// Converts tightly packed little-endian pixels, laid out as described by `masks`, into RGB pixels.
+fn masked_to_rgb(pixels: &[u8], bytes_per_pixel: usize, masks: ChannelMasks) -> Vec<u8> {
+	pixels
+		.chunks_exact(bytes_per_pixel)
+		.flat_map(|src| {
+			let mut value = [0_u8; 4];
+			value[..bytes_per_pixel].copy_from_slice(src);
+			let [r, g, b, _] = masks.decode(u32::from_le_bytes(value));
+			[r, g, b]
+		})
+		.collect()
+}
+
+/// Flattens an RGBA image onto a white background, for programs that don't understand transparency.
+pub fn flatten_onto_white(image: &RgbaImage) -> RgbImage {
+	RgbImage::from_fn(image.width(), image.height(), |x, y| {
+		let [r, g, b, a] = image.get_pixel(x, y).0;
+		let blend = |channel: u8| {
+			let (channel, alpha) = (u32::from(channel), u32::from(a));
+			((channel * alpha + 255 * (255 - alpha) + 127) / 255) as u8
+		};
+		image::Rgb([blend(r), blend(g), blend(b)])
+	})
+}
+
+/// Builds a 24-bit, bottom-up `CF_DIB` from an RGB image.
+pub fn dib_from_rgb(image: &RgbImage) -> Result<LockedPtr<u8>> {
+	let (width, height) = image.dimensions();
+	let stride = stride(24, width)?;
+	let header = BITMAPINFOHEADER {
+		biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+		biWidth: width as i32,
+		biHeight: height as i32,
+		biPlanes: 1,
+		biBitCount: 24,
+		biCompression: BI_RGB as u32,
+		biSizeImage: size_image(stride, height)?,
+		..Default::default()
+	};
+	write_dib(header, stride, 3, image.rows(), |pixel, dst| {
+		let [r, g, b] = pixel.0;
+		dst.copy_from_slice(&[b, g, r]);
+	})
+}
+
+/// Builds a 32-bit, bottom-up `CF_DIBV5` with an alpha channel from an RGBA image.
+///
+/// The pixels are stored as straight (not premultiplied) BGRA, which is what most programs expect.
+pub fn dibv5_from_rgba(image: &RgbaImage) -> Result<LockedPtr<u8>> {
+	let (width, height) = image.dimensions();
+	let stride = stride(32, width)?;
+	let header = BITMAPV5HEADER {
+		bV5Size: std::mem::size_of::<BITMAPV5HEADER>() as u32,
+		bV5Width: width as i32,
+		bV5Height: height as i32,
+		bV5Planes: 1,
+		bV5BitCount: 32,
+		bV5Compression: BI_BITFIELDS as u32,
+		bV5SizeImage: size_image(stride, height)?,
+		bV5RedMask: 0x00FF_0000,
+		bV5GreenMask: 0x0000_FF00,
+		bV5BlueMask: 0x0000_00FF,
+		bV5AlphaMask: 0xFF00_0000,
+		bV5CSType: LCS_SRGB,
+		bV5Intent: LCS_GM_IMAGES as u32,
+		..Default::default()
+	};
+	write_dib(header, stride, 4, image.rows(), |pixel, dst| {
+		let [r, g, b, a] = pixel.0;
+		dst.copy_from_slice(&[b, g, r, a]);
+	})
+}
+
+/// Allocates global memory for a DIB, and writes the header followed by the pixels, bottom row first.
+fn write_dib<Header: Copy, Rows, Pixel>(
+	header: Header,
+	stride: usize,
+	bytes_per_pixel: usize,
+	rows: Rows,
+	write_pixel: impl Fn(&Pixel, &mut [u8]),
+) -> Result<LockedPtr<u8>>
+where
+	Rows: ExactSizeIterator,
+	Rows::Item: Iterator<Item = Pixel>,
+{
+	let header_size = std::mem::size_of::<Header>();
+	let height = rows.len();
+	let pixels_len = stride.checked_mul(height).ok_or(Error::InvalidImage)?;
+	let mut memory = LockedPtr::<u8>::alloc_zeroed(lock::checked_len(pixels_len, header_size)?)?;
+	unsafe { std::ptr::write_unaligned(memory.as_mut_ptr() as *mut Header, header) };
+	let data = &mut memory.as_bytes_mut()?[header_size..];
+	for (y, row) in rows.enumerate() {
+		// Bottom-up DIBs start with the last row of the image.
+		let dst_row = &mut data[(height - 1 - y) * stride..][..stride];
+		for (pixel, dst) in row.zip(dst_row.chunks_exact_mut(bytes_per_pixel)) {
+			write_pixel(&pixel, dst);
+		}
+	}
+	Ok(memory)
+}
+
+/// Returns the number of bytes in a row of a DIB, including padding.
+///
+/// Fails with [`Error::InvalidImage`] if that doesn't fit in a `usize`, which a header from another program could ask for.
+fn stride(bits_per_pixel: u16, width: u32) -> Result<usize> {
+	usize::from(bits_per_pixel)
+		.checked_mul(width as usize)
+		.and_then(|bits| bits.div_ceil(32).checked_mul(4))
+		.ok_or(Error::InvalidImage)
+}
+
+/// Returns the size of the pixels for a header's `biSizeImage`, which is only 32 bits.
+fn size_image(stride: usize, height: u32) -> Result<u32> {
+	stride
+		.checked_mul(height as usize)
+		.and_then(|size| u32::try_from(size).ok())
+		.ok_or(Error::InvalidImage)
+}
+
+/// Converts tightly packed BGR or BGRX pixels into RGB pixels, in place.
+fn bgr_to_rgb(pixels: &mut Vec<u8>, bytes_per_pixel: usize) -> Result<()> {
+	match bytes_per_pixel {
+		3 => pixels
+			.chunks_exact_mut(3)
+			.for_each(|pixel| pixel.swap(0, 2)),
+		4 => {
+			// Each RGB pixel ends before the BGRX pixel it came from, so this can be done front-to-back.
+			let count = pixels.len() / 4;
+			for idx in 0..count {
+				let [b, g, r] = [pixels[idx * 4], pixels[idx * 4 + 1], pixels[idx * 4 + 2]];
+				pixels[idx * 3..idx * 3 + 3].copy_from_slice(&[r, g, b]);
+			}
+			pixels.truncate(count * 3);
+		}
+		_ => return Err(Error::InvalidImage),
+	}
+	Ok(())
+}
+
+/// Removes the padding from the end of each row of a DIB, in place.
+///
+/// Every row's destination is at or before its source, so rows can be moved front-to-back without overlapping anything that hasn't been moved yet.
+fn compact_rows(pixels: &mut Vec<u8>, row_len: usize, stride: usize, height: usize) {
+	let rows = pixels
+		.len()
+		.checked_div(stride)
+		.map_or(0, |rows| rows.min(height));
+	if row_len != stride {
+		for row in 1..rows {
+			let src = row * stride;
+			pixels.copy_within(src..src + row_len, row * row_len);
+		}
+	}
+	pixels.truncate(rows * row_len);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compact_padded_rows() {
+		// A 3-pixel-wide 24bpp image has 9 bytes of pixels per row, padded to a 12 byte stride.
+		let mut pixels = Vec::new();
+		for row in 0..4_u8 {
+			pixels.extend((0..9).map(|idx| row * 10 + idx));
+			pixels.extend([0xFF; 3]);
+		}
+		compact_rows(&mut pixels, 9, 12, 4);
+		let expected = (0..4_u8)
+			.flat_map(|row| (0..9).map(move |idx| row * 10 + idx))
+			.collect::<Vec<_>>();
+		assert_eq!(pixels, expected);
+	}
+
+	#[test]
+	fn flatten_alpha() {
+		let image = RgbaImage::from_raw(3, 1, vec![0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 128])
+			.expect("invalid image");
+		let flat = flatten_onto_white(&image);
+		assert_eq!(flat.as_raw(), &[255, 255, 255, 0, 0, 0, 127, 127, 127]);
+	}
+
+	/// Packs 16-bit pixels the way they're stored in a DIB.
+	fn words(pixels: &[u16]) -> Vec<u8> {
+		pixels
+			.iter()
+			.flat_map(|pixel| pixel.to_le_bytes())
+			.collect()
+	}
+
+	#[test]
+	fn expand_rgb565() {
+		let masks = ChannelMasks {
+			red: 0xF800,
+			green: 0x07E0,
+			blue: 0x001F,
+			alpha: 0,
+		};
+		let pixels = words(&[0xF800, 0x07E0, 0x001F, 0xFFFF, 0x8410]);
+		assert_eq!(
+			masked_to_rgb(&pixels, 2, masks),
+			[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255, 132, 130, 132]
+		);
+	}
+
+	#[test]
+	fn expand_rgb555() {
+		let pixels = words(&[0x7C00, 0x03E0, 0x001F, 0x7FFF, 0x4210]);
+		assert_eq!(
+			masked_to_rgb(&pixels, 2, ChannelMasks::RGB555),
+			[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255, 132, 132, 132]
+		);
+	}
+
+	#[test]
+	fn masks_of_16bpp_dib() {
+		let mut header = BITMAPINFOHEADER {
+			biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+			biBitCount: 16,
+			biCompression: BI_RGB as u32,
+			..Default::default()
+		};
+		assert_eq!(
+			channel_masks(&[], &header).expect("failed to get masks"),
+			Some(ChannelMasks::RGB555)
+		);
+		header.biCompression = BI_BITFIELDS as u32;
+		let mut dib = vec![0_u8; header.biSize as usize];
+		dib.extend(
+			[0xF800_u32, 0x07E0, 0x001F]
+				.iter()
+				.flat_map(|mask| mask.to_le_bytes()),
+		);
+		assert_eq!(
+			channel_masks(&dib, &header).expect("failed to get masks"),
+			Some(ChannelMasks {
+				red: 0xF800,
+				green: 0x07E0,
+				blue: 0x001F,
+				alpha: 0,
+			})
+		);
+		assert_eq!(channel_masks(&[], &header), Err(Error::InvalidImage));
+	}
+
+	const RED: image::Rgb<u8> = image::Rgb([255, 0, 0]);
+	const GREEN: image::Rgb<u8> = image::Rgb([0, 255, 0]);
+	const BLUE: image::Rgb<u8> = image::Rgb([0, 0, 255]);
+	const WHITE: image::Rgb<u8> = image::Rgb([255, 255, 255]);
+
+	/// Builds a 2x2 24-bit DIB, with red and green on top, and blue and white below.
+	/// Rows are padded from 6 to 8 bytes.
+	fn dib_2x2(bottom_up: bool) -> (BITMAPINFOHEADER, Vec<u8>) {
+		let top = [0, 0, 255, 0, 255, 0, 0, 0];
+		let bottom = [255, 0, 0, 255, 255, 255, 0, 0];
+		let rows = if bottom_up {
+			[bottom, top]
+		} else {
+			[top, bottom]
+		};
+		let header = BITMAPINFOHEADER {
+			biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+			biWidth: 2,
+			biHeight: if bottom_up { 2 } else { -2 },
+			biPlanes: 1,
+			biBitCount: 24,
+			biCompression: BI_RGB as u32,
+			biSizeImage: 16,
+			..Default::default()
+		};
+		(header, rows.concat())
+	}
+
+	fn assert_2x2(image: &RgbImage) {
+		assert_eq!(image.get_pixel(0, 0), &RED, "top-left pixel is wrong");
+		assert_eq!(image.get_pixel(1, 0), &GREEN);
+		assert_eq!(image.get_pixel(0, 1), &BLUE);
+		assert_eq!(image.get_pixel(1, 1), &WHITE);
+	}
+
+	#[test]
+	fn bottom_up_dib() {
+		let (header, pixels) = dib_2x2(true);
+		assert_2x2(&decode(pixels, &header, None).expect("failed to decode image"));
+	}
+
+	#[test]
+	fn top_down_dib() {
+		let (header, pixels) = dib_2x2(false);
+		assert_2x2(&decode(pixels, &header, None).expect("failed to decode image"));
+	}
+
+	#[test]
+	fn zero_size_image() {
+		let (mut header, pixels) = dib_2x2(true);
+		header.biSizeImage = 0;
+		assert_eq!(image_size(&header), Ok(16));
+		let mut dib = vec![0_u8; header.biSize as usize];
+		dib.extend(&pixels);
+		assert_eq!(check_dib_size(&dib, &header), Ok(()));
+		assert_2x2(&decode(pixels, &header, None).expect("failed to decode image"));
+	}
+
+	#[test]
+	fn lying_size_image() {
+		let (mut header, pixels) = dib_2x2(true);
+		let mut dib = vec![0_u8; header.biSize as usize];
+		dib.extend(&pixels);
+		assert_eq!(check_dib_size(&dib, &header), Ok(()));
+		header.biSizeImage = 1024;
+		assert_eq!(
+			check_dib_size(&dib, &header),
+			Err(Error::AllocationTooSmall {
+				needed: 1064,
+				actual: 56
+			})
+		);
+	}
+
+	#[test]
+	fn truncated_pixels_without_size_image() {
+		let (mut header, pixels) = dib_2x2(true);
+		header.biSizeImage = 0;
+		let mut dib = vec![0_u8; header.biSize as usize];
+		dib.extend(&pixels[..8]);
+		assert_eq!(
+			check_dib_size(&dib, &header),
+			Err(Error::AllocationTooSmall {
+				needed: 56,
+				actual: 48
+			})
+		);
+	}
+
+	#[test]
+	fn color_table_is_counted() {
+		let (mut header, pixels) = dib_2x2(true);
+		header.biBitCount = 32;
+		header.biCompression = BI_BITFIELDS as u32;
+		header.biSizeImage = 0;
+		// At 32bpp, the pixels take up 16 bytes, which is all there is after the header, leaving no room for the masks.
+		let mut dib = vec![0_u8; header.biSize as usize];
+		dib.extend(&pixels);
+		assert_eq!(
+			check_dib_size(&dib, &header),
+			Err(Error::AllocationTooSmall {
+				needed: 68,
+				actual: 56
+			})
+		);
+		header.biBitCount = 8;
+		header.biCompression = BI_RGB as u32;
+		header.biClrUsed = 2;
+		assert_eq!(color_table_size(&header), 8);
+		header.biClrUsed = 0;
+		assert_eq!(color_table_size(&header), 1024);
+	}
+
+	#[test]
+	fn overflowing_dimensions() {
+		assert_eq!(stride(32, 3), Ok(12));
+		let header = BITMAPINFOHEADER {
+			biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+			biWidth: i32::MAX,
+			biHeight: i32::MIN,
+			biBitCount: u16::MAX,
+			..Default::default()
+		};
+		assert_eq!(image_size(&header), Err(Error::InvalidImage));
+		assert_eq!(size_image(usize::MAX, 2), Err(Error::InvalidImage));
+		assert_eq!(size_image(4, u32::MAX), Err(Error::InvalidImage));
+	}
+
+	/// Packs 32-bit pixels the way they're stored in a DIB.
+	fn dwords(pixels: &[u32]) -> Vec<u8> {
+		pixels
+			.iter()
+			.flat_map(|pixel| pixel.to_le_bytes())
+			.collect()
+	}
+
+	#[test]
+	fn bgra_masked_pixels() {
+		let masks = ChannelMasks {
+			alpha: 0xFF00_0000,
+			..ChannelMasks::BGR
+		};
+		let pixels = [[0x10, 0x20, 0x30, 0xFF], [0xFF, 0x00, 0x80, 0x00]].concat();
+		assert_eq!(
+			masked_to_rgb(&pixels, 4, masks),
+			[0x30, 0x20, 0x10, 0x80, 0x00, 0xFF]
+		);
+	}
+
+	#[test]
+	fn rgba_masked_pixels() {
+		let masks = ChannelMasks {
+			red: 0x0000_00FF,
+			green: 0x0000_FF00,
+			blue: 0x00FF_0000,
+			alpha: 0xFF00_0000,
+		};
+		let pixels = [[0x10, 0x20, 0x30, 0xFF], [0xFF, 0x00, 0x80, 0x00]].concat();
+		assert_eq!(
+			masked_to_rgb(&pixels, 4, masks),
+			[0x10, 0x20, 0x30, 0xFF, 0x00, 0x80]
+		);
+	}
+
+	#[test]
+	fn ten_bit_masked_pixels() {
+		// 2:10:10:10, with red in the lowest bits.
+		let masks = ChannelMasks {
+			red: 0x0000_03FF,
+			green: 0x000F_FC00,
+			blue: 0x3FF0_0000,
+			alpha: 0xC000_0000,
+		};
+		let pixels = dwords(&[0x3FF0_0000, 0x0000_03FF | (0x200 << 10)]);
+		assert_eq!(masked_to_rgb(&pixels, 4, masks), [0, 0, 255, 255, 128, 0]);
+	}
+
+	#[test]
+	fn masks_of_32bpp_dib() {
+		let mut header = BITMAPINFOHEADER {
+			biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+			biBitCount: 32,
+			biCompression: BI_RGB as u32,
+			..Default::default()
+		};
+		assert_eq!(channel_masks(&[], &header), Ok(None));
+		// The masks of a V5 header are in the header itself, where they'd otherwise follow it.
+		let v5 = BITMAPV5HEADER {
+			bV5Size: std::mem::size_of::<BITMAPV5HEADER>() as u32,
+			bV5BitCount: 32,
+			bV5Compression: BI_BITFIELDS as u32,
+			bV5RedMask: 0x0000_00FF,
+			bV5GreenMask: 0x0000_FF00,
+			bV5BlueMask: 0x00FF_0000,
+			bV5AlphaMask: 0xFF00_0000,
+			..Default::default()
+		};
+		let dib = unsafe {
+			std::slice::from_raw_parts(
+				&v5 as *const BITMAPV5HEADER as *const u8,
+				std::mem::size_of::<BITMAPV5HEADER>(),
+			)
+		};
+		header.biSize = v5.bV5Size;
+		header.biCompression = BI_BITFIELDS as u32;
+		assert_eq!(
+			channel_masks(dib, &header),
+			Ok(Some(ChannelMasks {
+				red: 0x0000_00FF,
+				green: 0x0000_FF00,
+				blue: 0x00FF_0000,
+				alpha: 0,
+			}))
+		);
+	}
+
+	#[test]
+	fn compact_unpadded_rows() {
+		let mut pixels = (0..24_u8).collect::<Vec<_>>();
+		compact_rows(&mut pixels, 12, 12, 2);
+		assert_eq!(pixels, (0..24_u8).collect::<Vec<_>>());
+	}
+}
@@ -2,61 +2,215 @@
 
 use crate::{
 	error::{Error, Result, WindowsError},
-	lock::LockedPtr,
+	lock::{self, LockedPtr},
+};
+use std::{
+	ffi::OsString,
+	os::windows::ffi::{OsStrExt, OsStringExt},
+	path::{Path, PathBuf},
 };
-use std::path::PathBuf;
 use windows::Win32::{
-	Foundation::HANDLE,
-	UI::Shell::{DragQueryFileW, HDROP},
+	Foundation::{SetLastError, BOOL, HANDLE, NO_ERROR, POINT},
+	UI::Shell::{DragQueryFileW, DROPFILES, HDROP},
 };
-use wtf8::Wtf8Buf;
 
 pub fn get(handle: HANDLE) -> Result<Vec<PathBuf>> {
 	let locked_hdrop = unsafe { LockedPtr::<()>::new(handle) }?;
-	DropHandle::from(HDROP(locked_hdrop.as_ptr() as isize)).get_files()
+	DropHandle::from(locked_hdrop.as_raw_handle().as_hdrop()).get_files()
+}
+
+/// Counts the files in an HDROP, without getting any of their paths.
+pub fn count(handle: HANDLE) -> Result<usize> {
+	let locked_hdrop = unsafe { LockedPtr::<()>::new(handle) }?;
+	DropHandle::from(locked_hdrop.as_raw_handle().as_hdrop()).count()
+}
+
+/// Gets the path of a single file in an HDROP, or `None` if there aren't that many files.
+pub fn nth(handle: HANDLE, idx: usize) -> Result<Option<PathBuf>> {
+	let locked_hdrop = unsafe { LockedPtr::<()>::new(handle) }?;
+	let drop_handle = DropHandle::from(locked_hdrop.as_raw_handle().as_hdrop());
+	if idx >= drop_handle.count()? {
+		return Ok(None);
+	}
+	drop_handle.get_file(idx).map(Some)
+}
+
+/// Options for how a list of files is put on the clipboard.
+///
+/// The defaults match what [`ClipboardHandleInner::set_files`](crate::ClipboardHandleInner::set_files) does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SetFilesOptions {
+	point: (i32, i32),
+	non_client: bool,
+}
+
+impl SetFilesOptions {
+	/// Sets the point that the files are dropped at, which some drop targets use to decide where they go.
+	///
+	/// Defaults to `(0, 0)`.
+	pub fn point(mut self, x: i32, y: i32) -> Self {
+		self.point = (x, y);
+		self
+	}
+
+	/// Sets whether the drop point is in screen coordinates, rather than in the client coordinates
+	/// of the window that the files are dropped on.
+	///
+	/// Defaults to `true`.
+	pub fn non_client(mut self, non_client: bool) -> Self {
+		self.non_client = non_client;
+		self
+	}
+}
+
+impl Default for SetFilesOptions {
+	fn default() -> Self {
+		Self {
+			point: (0, 0),
+			non_client: true,
+		}
+	}
+}
+
+/// Builds the `CF_HDROP` data for a list of files: a `DROPFILES` header, followed by the paths.
+///
+/// Empty paths are skipped, as they'd end the list early. Fails with [`Error::NoFiles`] if there aren't any paths left.
+pub fn encode<I>(paths: I, options: &SetFilesOptions) -> Result<Vec<u8>>
+where
+	I: IntoIterator,
+	I::Item: AsRef<Path>,
+{
+	let list = wide_list(paths)?;
+	let mut bytes = vec![0; encoded_len(&list)?];
+	write(&list, options, &mut bytes);
+	Ok(bytes)
+}
+
+/// Like [`encode`], but builds the list straight into global memory, ready to be put on the clipboard.
+pub(crate) fn alloc<I>(paths: I, options: &SetFilesOptions) -> Result<LockedPtr<u8>>
+where
+	I: IntoIterator,
+	I::Item: AsRef<Path>,
+{
+	let list = wide_list(paths)?;
+	let mut memory = LockedPtr::<u8>::alloc_zeroed(encoded_len(&list)?)?;
+	write(&list, options, memory.as_bytes_mut()?);
+	Ok(memory)
+}
+
+/// Encodes the paths as UTF-16 code units, each followed by a null terminator, with a second one ending the list.
+///
+/// Paths are encoded as they are, even if they aren't valid Unicode, as they still name a file.
+fn wide_list<I>(paths: I) -> Result<Vec<u16>>
+where
+	I: IntoIterator,
+	I::Item: AsRef<Path>,
+{
+	let mut paths = paths
+		.into_iter()
+		.filter(|path| !path.as_ref().as_os_str().is_empty())
+		.peekable();
+	if paths.peek().is_none() {
+		return Err(Error::NoFiles);
+	}
+	let mut list = Vec::new();
+	for path in paths {
+		list.extend(path.as_ref().as_os_str().encode_wide());
+		list.push(0);
+	}
+	// The list is double-null-terminated, so we need a SECOND null terminator here!
+	list.push(0);
+	Ok(list)
+}
+
+/// How many bytes the header and list take up.
+fn encoded_len(list: &[u16]) -> Result<usize> {
+	let list_len = list.len().checked_mul(2).ok_or(Error::AllocationOverflow {
+		elements: list.len(),
+		element_size: 2,
+	})?;
+	lock::checked_len(list_len, std::mem::size_of::<DROPFILES>())
+}
+
+/// Writes the header and list into memory of the length from [`encoded_len`].
+fn write(list: &[u16], options: &SetFilesOptions, dst: &mut [u8]) {
+	// microsoft never intended anyone to manually create this but fuck you I do what I want.
+	let drop_files = DROPFILES {
+		pFiles: std::mem::size_of::<DROPFILES>() as u32,
+		pt: POINT {
+			x: options.point.0,
+			y: options.point.1,
+		},
+		fNC: BOOL::from(options.non_client),
+		fWide: BOOL(1),
+	};
+	let (header, dst) = dst.split_at_mut(std::mem::size_of::<DROPFILES>());
+	// DROPFILES is plain old data, so its bytes can be copied straight out of it.
+	header.copy_from_slice(unsafe {
+		std::slice::from_raw_parts(
+			&drop_files as *const DROPFILES as *const u8,
+			std::mem::size_of::<DROPFILES>(),
+		)
+	});
+	for (dst, unit) in dst.chunks_exact_mut(2).zip(list) {
+		dst.copy_from_slice(&unit.to_le_bytes());
+	}
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 struct DropHandle(HDROP);
 
 impl DropHandle {
-	pub fn get_files(&self) -> Result<Vec<PathBuf>> {
-		// Figure out how many files are in this HDROP
+	pub fn count(&self) -> Result<usize> {
+		// Figure out how many files are in this HDROP.
+		// Zero is both the error value and a valid count, so the last error has to be cleared to tell them apart.
+		unsafe { SetLastError(NO_ERROR) };
 		let file_count = unsafe { DragQueryFileW(self.0, u32::MAX, &mut []) } as usize;
 		if file_count == 0 {
-			return Err(Error::PathCount(WindowsError::from_last_error()));
+			if let Some(err) = WindowsError::try_from_last_error() {
+				return Err(Error::PathCount(err));
+			}
 		}
+		Ok(file_count)
+	}
+
+	pub fn get_files(&self) -> Result<Vec<PathBuf>> {
+		let file_count = self.count()?;
 		// Allocate a Vec of PathBufs big enough to handle all those files
 		let mut out = Vec::with_capacity(file_count);
-		// Iterate through all the files
+		// Iterate through all the files, adding each path to the output Vec.
 		for idx in 0..file_count {
-			// Figure out how big our buffer needs to be to fit this file path
-			let needed_len = unsafe { DragQueryFileW(self.0, idx as u32, &mut []) } as usize;
-			if needed_len == 0 {
-				return Err(Error::PathLength {
-					idx,
-					err: WindowsError::from_last_error(),
-				});
-			}
-			// Allocate the buffer where we'll store the file path
-			let mut buf = vec![0_u16; needed_len + 1];
-			// Get the file path, storing it in our buffer, and getting the total bytes written to our buffer.
-			let written_len = unsafe { DragQueryFileW(self.0, idx as u32, &mut buf) } as usize;
-			if written_len == 0 {
-				return Err(Error::FilePath {
-					idx,
-					err: WindowsError::from_last_error(),
-				});
-			}
-			// Truncate any unwritten bytes off our buffer.
-			buf.truncate(written_len);
-			// Convert our buffer to a CString, then to a String, and then into a PathBuf. Should take zero allocations.
-			let path = PathBuf::from(Wtf8Buf::from_ill_formed_utf16(&buf).into_string_lossy());
-			// Add our path to the output Vec.
-			out.push(path);
+			out.push(self.get_file(idx)?);
 		}
 		Ok(out)
 	}
+
+	pub fn get_file(&self, idx: usize) -> Result<PathBuf> {
+		// Figure out how big our buffer needs to be to fit this file path
+		let needed_len = unsafe { DragQueryFileW(self.0, idx as u32, &mut []) } as usize;
+		if needed_len == 0 {
+			return Err(Error::PathLength {
+				idx,
+				err: WindowsError::from_last_error(),
+			});
+		}
+		// Allocate the buffer where we'll store the file path
+		let mut buf = vec![0_u16; needed_len + 1];
+		// Get the file path, storing it in our buffer, and getting the total bytes written to our buffer.
+		let written_len = unsafe { DragQueryFileW(self.0, idx as u32, &mut buf) } as usize;
+		if written_len == 0 {
+			return Err(Error::FilePath {
+				idx,
+				err: WindowsError::from_last_error(),
+			});
+		}
+		// Truncate any unwritten bytes off our buffer.
+		buf.truncate(written_len);
+		// Paths can be any sequence of UTF-16 code units, valid Unicode or not, and so can an OsString.
+		// Anything lossy here would give us a path that doesn't name the file anymore.
+		Ok(PathBuf::from(OsString::from_wide(&buf)))
+	}
 }
 
 impl From<HDROP> for DropHandle {
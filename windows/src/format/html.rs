@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Best-effort text extraction from the CF_HTML format, which is an HTML document with a header
+//! saying where the copied fragment starts and ends.
+
+use crate::{
+	error::{Error, Result},
+	format::string::until_nul,
+	lock::LockedPtr,
+};
+use windows::Win32::Foundation::HANDLE;
+
+/// Gets the text content of the HTML fragment, see [`to_text`].
+pub fn get_text(handle: HANDLE) -> Result<String> {
+	let locked = unsafe { LockedPtr::<u8>::new(handle) }?;
+	let data = until_nul(locked.as_slice()?);
+	let fragment = fragment(data).ok_or(Error::InvalidHtml)?;
+	Ok(to_text(&String::from_utf8_lossy(fragment)))
+}
+
+/// Returns the part of the CF_HTML data that the `StartFragment` and `EndFragment` byte offsets point to.
+pub fn fragment(data: &[u8]) -> Option<&[u8]> {
+	let start = header_value(data, "StartFragment:")?;
+	// Some programs count the null terminator, or get the end slightly wrong.
+	let end = header_value(data, "EndFragment:")?.min(data.len());
+	data.get(start..end)
+}
+
+/// Builds CF_HTML data for a fragment, with the surrounding markup before and after it.
+///
+/// The fragment is wrapped in the `<!--StartFragment-->` and `<!--EndFragment-->` comments that
+/// programs reading it may look for, and the header's offsets point to the document and the fragment.
+///
+/// This only exists so the unit and integration tests can share their fixtures, and isn't part of the API.
+#[doc(hidden)]
+pub fn encode(before: &str, fragment: &str, after: &str) -> Vec<u8> {
+	const HEADER_LEN: usize = "Version:0.9\r\nStartHTML:0000000000\r\nEndHTML:0000000000\r\nStartFragment:0000000000\r\nEndFragment:0000000000\r\n".len();
+	let start = HEADER_LEN + before.len() + "<!--StartFragment-->".len();
+	let end = start + fragment.len();
+	let html = format!(
+		"{}<!--StartFragment-->{}<!--EndFragment-->{}",
+		before, fragment, after
+	);
+	format!(
+		"Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n{}",
+		HEADER_LEN,
+		HEADER_LEN + html.len(),
+		start,
+		end,
+		html
+	)
+	.into_bytes()
+}
+
+/// Parses a number from one of the `Key:value` lines that make up the header.
+fn header_value(data: &[u8], key: &str) -> Option<usize> {
+	// The header is everything before the HTML itself starts.
+	let header_len = data.iter().position(|&b| b == b'<').unwrap_or(data.len());
+	let header = std::str::from_utf8(&data[..header_len]).ok()?;
+	header
+		.lines()
+		.find_map(|line| line.trim().strip_prefix(key))
+		.and_then(|value| value.trim().parse().ok())
+}
+
+/// Strips the tags from an HTML fragment, leaving its text.
+///
+/// This is far from what a browser would render: whitespace is collapsed, `<br>` becomes a line break,
+/// and `<p>`, `<div>`, `<li>` and `<tr>` start a new line, but everything else is thrown away.
+/// The contents of `<script>` and `<style>` are skipped, and the common named entities and all numeric
+/// entities are decoded.
+pub fn to_text(html: &str) -> String {
+	let mut text = Text::default();
+	let mut rest = html;
+	while let Some(c) = rest.chars().next() {
+		match c {
+			'<' => {
+				if let Some(comment) = rest.strip_prefix("<!--") {
+					rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+					continue;
+				}
+				let (tag, after) = match rest.find('>') {
+					Some(end) => (&rest[1..end], &rest[end + 1..]),
+					None => (&rest[1..], ""),
+				};
+				rest = after;
+				let closing = tag.starts_with('/');
+				let name = tag
+					.trim_start_matches('/')
+					.split(|c: char| c.is_whitespace() || c == '/')
+					.next()
+					.unwrap_or_default()
+					.to_ascii_lowercase();
+				match name.as_str() {
+					"br" => text.line_break(),
+					"p" | "div" | "li" | "tr" => text.new_line(),
+					"script" | "style" if !closing => {
+						// Lowercasing ASCII doesn't change any lengths, so the offset is the same in both.
+						let close = format!("</{}", name);
+						rest = rest
+							.to_ascii_lowercase()
+							.find(&close)
+							.map_or("", |end| &rest[end..]);
+					}
+					_ => {}
+				}
+			}
+			'&' => match entity(rest) {
+				Some((decoded, len)) => {
+					text.push(decoded);
+					rest = &rest[len..];
+				}
+				None => {
+					text.push('&');
+					rest = &rest[1..];
+				}
+			},
+			c => {
+				if c.is_ascii_whitespace() {
+					text.pending_space = true;
+				} else {
+					text.push(c);
+				}
+				rest = &rest[c.len_utf8()..];
+			}
+		}
+	}
+	let len = text.text.trim_end().len();
+	text.text.truncate(len);
+	text.text
+}
+
+/// Text that's being extracted from HTML, with whitespace collapsed like a browser would.
+#[derive(Default)]
+struct Text {
+	text: String,
+	/// Whether there was whitespace since the last character, which becomes a single space
+	/// if more text follows on the same line.
+	pending_space: bool,
+}
+
+impl Text {
+	fn push(&mut self, c: char) {
+		if self.pending_space && !self.text.is_empty() && !self.text.ends_with('\n') {
+			self.text.push(' ');
+		}
+		self.pending_space = false;
+		self.text.push(c);
+	}
+
+	fn line_break(&mut self) {
+		self.text.push('\n');
+		self.pending_space = false;
+	}
+
+	/// Starts a new line, unless we're already at the start of one.
+	fn new_line(&mut self) {
+		if !self.text.is_empty() && !self.text.ends_with('\n') {
+			self.text.push('\n');
+		}
+		self.pending_space = false;
+	}
+}
+
+/// Decodes the entity at the start of `s`, returning the character and how many bytes it took up.
+fn entity(s: &str) -> Option<(char, usize)> {
+	// The longest entity we decode is `&#x10FFFF;`.
+	let end = s.char_indices().take(10).find(|&(_, c)| c == ';')?.0;
+	let decoded = match &s[1..end] {
+		"amp" => '&',
+		"lt" => '<',
+		"gt" => '>',
+		"quot" => '"',
+		"apos" => '\'',
+		"nbsp" => '\u{a0}',
+		name => {
+			let number = name.strip_prefix('#')?;
+			let code = match number.strip_prefix(|c| c == 'x' || c == 'X') {
+				Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+				None => number.parse().ok()?,
+			};
+			char::from_u32(code)?
+		}
+	};
+	Some((decoded, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn nested_tags() {
+		assert_eq!(
+			to_text("<div><p>Hello, <b><i>world</i></b>!</p><ul><li>one</li><li>two<br>lines</li></ul></div>"),
+			"Hello, world!\none\ntwo\nlines"
+		);
+	}
+
+	#[test]
+	fn collapse_whitespace() {
+		assert_eq!(
+			to_text("<p>\r\n  spread\tout \r\n  text  </p>\r\n<p>  next</p>"),
+			"spread out text\nnext"
+		);
+	}
+
+	#[test]
+	fn entities() {
+		assert_eq!(
+			to_text("a &lt;b&gt; &amp;amp; &quot;c&quot; &apos;d&apos;&nbsp;&#233;&#x1F980; &unknown; & e"),
+			"a <b> &amp; \"c\" 'd'\u{a0}é🦀 &unknown; & e"
+		);
+	}
+
+	#[test]
+	fn skip_comments_and_scripts() {
+		assert_eq!(
+			to_text("<!-- a > b --><style>p > b { color: red }</style>shown<SCRIPT>if (a < b) {}</SCRIPT>"),
+			"shown"
+		);
+	}
+
+	#[test]
+	fn fragment_mid_document() {
+		let data = encode(
+			"<html><body>\r\n<p>before</p>",
+			"<b>copied</b> text",
+			"<p>after</p>\r\n</body></html>",
+		);
+		let fragment = fragment(&data).expect("failed to find fragment");
+		assert_eq!(fragment, b"<b>copied</b> text");
+		assert_eq!(
+			to_text(std::str::from_utf8(fragment).unwrap()),
+			"copied text"
+		);
+	}
+
+	#[test]
+	fn missing_offsets() {
+		assert_eq!(
+			fragment(b"Version:0.9\r\nStartHTML:0000000000\r\n<p>hi</p>"),
+			None
+		);
+		assert_eq!(
+			fragment(b"Version:0.9\r\nStartFragment:100\r\nEndFragment:10\r\n<p>hi</p>"),
+			None
+		);
+	}
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Image file formats that clipboard images can be saved as.
+
+use crate::error::{Error, Result};
+use image::ImageFormat;
+use std::path::Path;
+
+/// Picks the format to save an image as from the extension of `path`.
+pub(crate) fn format_for_path(path: &Path) -> Result<ImageFormat> {
+	match ImageFormat::from_path(path) {
+		Ok(
+			format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Bmp | ImageFormat::Tiff),
+		) => Ok(format),
+		_ => Err(Error::ImageExtension(path.to_path_buf())),
+	}
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::error::{Error, Result, WindowsError};
+use windows::Win32::Graphics::Gdi::{GetEnhMetaFileBits, SetEnhMetaFileBits, HENHMETAFILE};
+
+/// Copies the raw EMF bytes out of an enhanced metafile handle.
+///
+/// The handle belongs to the clipboard, so it isn't deleted.
+pub fn get(metafile: HENHMETAFILE, max_size: Option<usize>) -> Result<Vec<u8>> {
+	// First, ask how big the metafile is.
+	let size = self::size(metafile)?;
+	if let Some(limit) = max_size {
+		if size > limit {
+			return Err(Error::PayloadTooLarge { size, limit });
+		}
+	}
+	// Then actually copy it into our buffer.
+	let mut bytes = vec![0_u8; size];
+	let written = unsafe { GetEnhMetaFileBits(metafile, size as u32, bytes.as_mut_ptr()) };
+	if written == 0 {
+		return Err(Error::ReadMetafile(WindowsError::from_last_error()));
+	}
+	bytes.truncate(written as usize);
+	Ok(bytes)
+}
+
+/// Returns the size of the raw EMF bytes of an enhanced metafile handle, without copying them.
+pub fn size(metafile: HENHMETAFILE) -> Result<usize> {
+	let size = unsafe { GetEnhMetaFileBits(metafile, 0, std::ptr::null_mut()) };
+	if size == 0 {
+		return Err(Error::ReadMetafile(WindowsError::from_last_error()));
+	}
+	Ok(size as usize)
+}
+
+/// Creates an enhanced metafile from raw EMF bytes.
+///
+/// Once this is put on the clipboard, the clipboard owns it.
+pub fn create(bytes: &[u8]) -> Result<HENHMETAFILE> {
+	let metafile = unsafe { SetEnhMetaFileBits(bytes.len() as u32, bytes.as_ptr()) };
+	if metafile.is_invalid() {
+		return Err(Error::CreateMetafile(WindowsError::from_last_error()));
+	}
+	Ok(metafile)
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{error::Result, lock::LockedPtr};
+use windows::Win32::Foundation::HANDLE;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Copies the PNG file out of the registered `PNG` format, without whatever the allocation was rounded up with.
+pub fn get(handle: HANDLE) -> Result<Vec<u8>> {
+	let memory = unsafe { LockedPtr::<u8>::new(handle) }?;
+	let bytes = memory.as_bytes()?;
+	Ok(bytes[..len(bytes)].to_vec())
+}
+
+/// Returns how long a PNG file is, which is up to the end of its `IEND` chunk.
+///
+/// If it isn't a PNG file, or it ends before its `IEND` chunk does, all of `bytes` is taken to be the file.
+pub(crate) fn len(bytes: &[u8]) -> usize {
+	if !bytes.starts_with(SIGNATURE) {
+		return bytes.len();
+	}
+	let mut offset = SIGNATURE.len();
+	// Every chunk is its length, its type, its data, and a CRC of the type and data.
+	while let Some(header) = bytes.get(offset..offset + 8) {
+		let data_len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+		let end = match usize::try_from(data_len)
+			.ok()
+			.and_then(|data_len| offset.checked_add(12)?.checked_add(data_len))
+		{
+			Some(end) if end <= bytes.len() => end,
+			_ => break,
+		};
+		if &header[4..] == b"IEND" {
+			return end;
+		}
+		offset = end;
+	}
+	bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+		let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+		chunk.extend_from_slice(kind);
+		chunk.extend_from_slice(data);
+		// The CRC isn't checked.
+		chunk.extend_from_slice(&[0; 4]);
+		chunk
+	}
+
+	#[test]
+	fn trims_padding() {
+		let mut png = SIGNATURE.to_vec();
+		png.extend(chunk(b"IHDR", &[1; 13]));
+		png.extend(chunk(b"IEND", &[]));
+		let len = png.len();
+		png.extend_from_slice(&[0; 7]);
+		assert_eq!(super::len(&png), len);
+	}
+
+	#[test]
+	fn keeps_truncated() {
+		let mut png = SIGNATURE.to_vec();
+		png.extend(chunk(b"IHDR", &[1; 13]));
+		png.truncate(png.len() - 2);
+		assert_eq!(len(&png), png.len());
+		assert_eq!(len(b"not a png"), 9);
+	}
+}
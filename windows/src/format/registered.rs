@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Names of well-known clipboard formats that have to be registered at runtime.
+
+/// Tells clipboard monitors and history tools to ignore the clipboard's contents.
+pub const EXCLUDE_CLIPBOARD_CONTENT_FROM_MONITOR_PROCESSING: &str =
+	"ExcludeClipboardContentFromMonitorProcessing";
+/// A DWORD which, when zero, keeps the clipboard's contents out of the Windows 10+ clipboard history.
+pub const CAN_INCLUDE_IN_CLIPBOARD_HISTORY: &str = "CanIncludeInClipboardHistory";
+/// A DWORD which, when zero, keeps the clipboard's contents from being synced to other devices.
+pub const CAN_UPLOAD_TO_CLOUD_CLIPBOARD: &str = "CanUploadToCloudClipboard";
+/// PNG file bytes, which programs that support transparency put alongside the usual bitmap formats.
+pub const PNG: &str = "PNG";
+/// An HTML fragment, wrapped in the CF_HTML header that says where the fragment starts and ends.
+pub const HTML: &str = "HTML Format";
+/// A URL as null-terminated UTF-16, which browsers put alongside the text of a copied link.
+pub const URL_WIDE: &str = "UniformResourceLocatorW";
+/// A URL as null-terminated ANSI text, for older programs.
+pub const URL: &str = "UniformResourceLocator";
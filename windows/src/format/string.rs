@@ -4,22 +4,146 @@ use crate::{
 	error::{Error, Result},
 	lock::LockedPtr,
 };
-use std::{ffi::CStr, os::raw::c_char};
+use std::{borrow::Cow, ffi::OsString, os::windows::ffi::OsStringExt};
 use windows::Win32::Foundation::HANDLE;
 use wtf8::Wtf8Buf;
 
 pub fn get(handle: HANDLE) -> Result<String> {
-	let locked_str = unsafe { LockedPtr::<c_char>::new(handle) }?;
-	let c_str = unsafe { CStr::from_ptr(locked_str.as_mut_ptr()) };
-	c_str
-		.to_str()
+	let locked_str = unsafe { LockedPtr::<u8>::new(handle) }?;
+	let bytes = until_nul(locked_str.as_slice()?);
+	std::str::from_utf8(bytes)
 		.map(ToOwned::to_owned)
 		.map_err(Error::InvalidString)
 }
 
+/// Text that was decoded as UTF-8, with anything invalid replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LossyString {
+	/// The decoded text, with invalid sequences replaced by U+FFFD.
+	pub string: String,
+	/// The bytes the text was decoded from, without the null terminator.
+	pub bytes: Vec<u8>,
+}
+
+impl LossyString {
+	/// Returns whether anything had to be replaced.
+	pub fn is_lossy(&self) -> bool {
+		std::str::from_utf8(&self.bytes).is_err()
+	}
+}
+
+pub fn get_lossy(handle: HANDLE) -> Result<LossyString> {
+	let locked_str = unsafe { LockedPtr::<u8>::new(handle) }?;
+	let bytes = until_nul(locked_str.as_slice()?).to_vec();
+	Ok(LossyString {
+		string: String::from_utf8_lossy(&bytes).into_owned(),
+		bytes,
+	})
+}
+
+/// What to do with a string that has a NUL character in it, when putting it on the clipboard.
+///
+/// Text on the clipboard ends at the first NUL, so anything after one would be silently cut off
+/// by whoever reads it, us included.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NulPolicy {
+	/// Fail with [`Error::InteriorNul`]. This is the default.
+	#[default]
+	Error,
+	/// Keep only the text before the first NUL, which is what would be read anyway.
+	Truncate,
+	/// Replace every NUL with another character.
+	///
+	/// Replacing NULs with NULs would leave them cut off all the same,
+	/// so a replacement of `'\0'` fails with [`Error::InteriorNul`] like [`NulPolicy::Error`] does.
+	ReplaceWith(char),
+}
+
+impl NulPolicy {
+	/// Applies the policy to a string, only copying it if a NUL has to be replaced.
+	pub(crate) fn apply(self, string: &str) -> Result<Cow<'_, str>> {
+		let position = match string.find('\0') {
+			Some(position) => position,
+			None => return Ok(Cow::Borrowed(string)),
+		};
+		match self {
+			Self::Error | Self::ReplaceWith('\0') => Err(Error::InteriorNul { position }),
+			Self::Truncate => Ok(Cow::Borrowed(&string[..position])),
+			Self::ReplaceWith(replacement) => Ok(Cow::Owned(
+				string.replace('\0', replacement.encode_utf8(&mut [0; 4])),
+			)),
+		}
+	}
+
+	/// Applies the policy to UTF-16 code units, only copying them if a NUL has to be replaced.
+	pub(crate) fn apply_wide(self, string: &[u16]) -> Result<Cow<'_, [u16]>> {
+		let position = match string.iter().position(|&unit| unit == 0) {
+			Some(position) => position,
+			None => return Ok(Cow::Borrowed(string)),
+		};
+		match self {
+			Self::Error | Self::ReplaceWith('\0') => Err(Error::InteriorNul { position }),
+			Self::Truncate => Ok(Cow::Borrowed(&string[..position])),
+			Self::ReplaceWith(replacement) => {
+				let mut buffer = [0; 2];
+				let replacement = replacement.encode_utf16(&mut buffer);
+				Ok(Cow::Owned(
+					string
+						.iter()
+						.flat_map(|unit| match unit {
+							0 => &*replacement,
+							unit => std::slice::from_ref(unit),
+						})
+						.copied()
+						.collect(),
+				))
+			}
+		}
+	}
+}
+
+/// The byte order mark, which some programs put at the start of their text.
+const BOM: u16 = 0xFEFF;
+
+/// Gets UTF-16 text, without the byte order mark if it starts with one.
 pub fn get_unicode(handle: HANDLE) -> Result<String> {
 	let locked_str = unsafe { LockedPtr::<u16>::new(handle) }?;
-	let len = locked_str.size()? / std::mem::size_of::<u16>() - 1;
-	let u16_str = unsafe { std::slice::from_raw_parts(locked_str.as_mut_ptr(), len) };
+	let u16_str = until_nul(locked_str.as_slice()?);
+	let u16_str = u16_str.strip_prefix(&[BOM]).unwrap_or(u16_str);
 	Ok(Wtf8Buf::from_ill_formed_utf16(u16_str).into_string_lossy())
 }
+
+/// Gets UTF-16 text, keeping the byte order mark if it starts with one.
+pub fn get_unicode_raw(handle: HANDLE) -> Result<String> {
+	let locked_str = unsafe { LockedPtr::<u16>::new(handle) }?;
+	let u16_str = until_nul(locked_str.as_slice()?);
+	Ok(Wtf8Buf::from_ill_formed_utf16(u16_str).into_string_lossy())
+}
+
+/// Gets UTF-16 text exactly as it is, including any unpaired surrogates.
+pub fn get_unicode_os(handle: HANDLE) -> Result<OsString> {
+	let locked_str = unsafe { LockedPtr::<u16>::new(handle) }?;
+	Ok(OsString::from_wide(until_nul(locked_str.as_slice()?)))
+}
+
+/// Percent-encodes the UTF-8 bytes of anything in a URL that isn't ASCII, for formats that can only hold ASCII.
+pub(crate) fn ascii_url(url: &str) -> String {
+	let mut ascii = String::with_capacity(url.len());
+	for byte in url.bytes() {
+		if byte.is_ascii() {
+			ascii.push(char::from(byte));
+		} else {
+			ascii.push_str(&format!("%{:02X}", byte));
+		}
+	}
+	ascii
+}
+
+/// Cuts a string off at its null terminator, or returns the whole slice if there isn't one.
+pub(crate) fn until_nul<T: Default + PartialEq>(slice: &[T]) -> &[T] {
+	let nul = T::default();
+	match slice.iter().position(|c| *c == nul) {
+		Some(len) => &slice[..len],
+		None => slice,
+	}
+}
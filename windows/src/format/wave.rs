@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{error::Result, lock::LockedPtr};
+use windows::Win32::Foundation::HANDLE;
+
+/// Copies the WAV file out of a `CF_WAVE`, without whatever the allocation was rounded up with.
+pub fn get(handle: HANDLE) -> Result<Vec<u8>> {
+	let memory = unsafe { LockedPtr::<u8>::new(handle) }?;
+	let bytes = memory.as_bytes()?;
+	Ok(bytes[..len(bytes)].to_vec())
+}
+
+/// Returns how long a WAV file is, from the size of its RIFF chunk, which covers everything after the first 8 bytes.
+///
+/// If there's no header, or it claims more than there is, all of `bytes` is taken to be the file.
+pub(crate) fn len(bytes: &[u8]) -> usize {
+	bytes
+		.get(4..8)
+		.and_then(|size| {
+			let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]);
+			usize::try_from(size).ok()?.checked_add(8)
+		})
+		.filter(|len| *len <= bytes.len())
+		.unwrap_or(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn trims_padding() {
+		let mut wave = b"RIFF\x04\0\0\0WAVE".to_vec();
+		wave.extend_from_slice(&[0; 4]);
+		assert_eq!(len(&wave), 12);
+	}
+
+	#[test]
+	fn keeps_truncated() {
+		assert_eq!(len(b"RIFF\xFF\0\0\0WAVE"), 12);
+		assert_eq!(len(b"RIF"), 3);
+	}
+}
@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Typed handles for the data that the clipboard deals in.
+//!
+//! The clipboard hands every format back as a plain `HANDLE`, even though some formats are really
+//! another kind of handle. This version of `windows` doesn't have `From` impls between them,
+//! or an `HGLOBAL` type at all, with the global memory functions taking a bare `isize` instead.
+//! This module is the only place that reaches inside one handle type to make another,
+//! so everywhere else can hold on to the type that a handle really is.
+
+use std::ffi::c_void;
+use windows::Win32::{
+	Foundation::{BOOL, HANDLE},
+	Graphics::Gdi::{HBITMAP, HENHMETAFILE},
+	System::Memory::{
+		GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GLOBAL_ALLOC_FLAGS,
+	},
+	UI::Shell::HDROP,
+};
+
+// Every handle is a pointer-sized integer, on 32-bit targets as much as on 64-bit ones,
+// so nothing is truncated when converting between them, or to and from pointers.
+const _: () = {
+	use std::mem::size_of;
+	assert!(size_of::<HANDLE>() == size_of::<*mut c_void>());
+	assert!(size_of::<HGlobal>() == size_of::<HANDLE>());
+	assert!(size_of::<HBITMAP>() == size_of::<HANDLE>());
+	assert!(size_of::<HENHMETAFILE>() == size_of::<HANDLE>());
+	assert!(size_of::<HDROP>() == size_of::<HANDLE>());
+};
+
+/// A handle to global memory, which is what most clipboard formats are stored in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub(crate) struct HGlobal(HANDLE);
+
+impl HGlobal {
+	/// Allocates global memory, returning `None` if it couldn't be.
+	pub unsafe fn alloc(flags: GLOBAL_ALLOC_FLAGS, size: usize) -> Option<Self> {
+		match GlobalAlloc(flags, size) {
+			0 => None,
+			handle => Some(Self(HANDLE(handle))),
+		}
+	}
+
+	/// Locks the memory, returning a pointer to it, or null if it couldn't be locked.
+	pub unsafe fn lock(self) -> *mut c_void {
+		GlobalLock(self.0 .0)
+	}
+
+	/// Decrements the memory's lock count.
+	pub unsafe fn unlock(self) -> BOOL {
+		GlobalUnlock(self.0 .0)
+	}
+
+	/// Returns the size of the memory in bytes, or 0 if the handle isn't valid.
+	pub unsafe fn size(self) -> usize {
+		GlobalSize(self.0 .0)
+	}
+
+	/// Frees the memory, which must not be on the clipboard.
+	pub unsafe fn free(self) {
+		GlobalFree(self.0 .0);
+	}
+
+	/// Returns whether a pointer from [`HGlobal::lock`] is the handle itself,
+	/// which is what `GMEM_FIXED` memory is locked as.
+	pub fn is_fixed_at(self, ptr: *const c_void) -> bool {
+		self.0 .0 == ptr as isize
+	}
+
+	/// Returns the lock count of moveable memory.
+	#[cfg(test)]
+	pub unsafe fn lock_count(self) -> u32 {
+		const GMEM_LOCKCOUNT: u32 = 0xFF;
+		windows::Win32::System::Memory::GlobalFlags(self.0 .0) & GMEM_LOCKCOUNT
+	}
+
+	/// The `CF_HDROP` data is global memory holding a `DROPFILES`, which is what an `HDROP` is.
+	pub fn as_hdrop(self) -> HDROP {
+		HDROP(self.0 .0)
+	}
+}
+
+impl From<HANDLE> for HGlobal {
+	fn from(handle: HANDLE) -> Self {
+		Self(handle)
+	}
+}
+
+impl From<HGlobal> for HANDLE {
+	fn from(memory: HGlobal) -> Self {
+		memory.0
+	}
+}
+
+/// A kind of handle that clipboard data can be, for getting it from, or putting it on, the clipboard.
+pub(crate) trait ClipboardData: Copy {
+	/// Takes the handle that `GetClipboardData` returned as this type.
+	fn from_clipboard(handle: HANDLE) -> Self;
+
+	/// Returns the handle to give to `SetClipboardData`.
+	fn into_clipboard(self) -> HANDLE;
+}
+
+impl ClipboardData for HANDLE {
+	fn from_clipboard(handle: HANDLE) -> Self {
+		handle
+	}
+
+	fn into_clipboard(self) -> HANDLE {
+		self
+	}
+}
+
+impl ClipboardData for HGlobal {
+	fn from_clipboard(handle: HANDLE) -> Self {
+		Self(handle)
+	}
+
+	fn into_clipboard(self) -> HANDLE {
+		self.0
+	}
+}
+
+/// The `CF_BITMAP` data is a GDI bitmap.
+impl ClipboardData for HBITMAP {
+	fn from_clipboard(handle: HANDLE) -> Self {
+		HBITMAP(handle.0)
+	}
+
+	fn into_clipboard(self) -> HANDLE {
+		HANDLE(self.0)
+	}
+}
+
+/// The `CF_ENHMETAFILE` data is an enhanced metafile, which is put on the clipboard as it is,
+/// rather than in global memory.
+impl ClipboardData for HENHMETAFILE {
+	fn from_clipboard(handle: HANDLE) -> Self {
+		HENHMETAFILE(handle.0)
+	}
+
+	fn into_clipboard(self) -> HANDLE {
+		HANDLE(self.0)
+	}
+}
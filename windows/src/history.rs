@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+	builder::ClipboardOptions,
+	error::{Error, Result, WindowsError},
+	format,
+	snapshot::ClipboardSnapshot,
+	window, ClipboardHandle, ClipboardHandleInner,
+};
+use parking_lot::Mutex;
+use std::{
+	collections::{hash_map::DefaultHasher, VecDeque},
+	hash::{Hash, Hasher},
+	sync::{mpsc, Arc},
+	thread::JoinHandle,
+	time::{Duration, SystemTime},
+};
+use windows::Win32::{
+	Foundation::{HWND, LPARAM, WPARAM},
+	System::DataExchange::{AddClipboardFormatListener, RemoveClipboardFormatListener},
+	UI::WindowsAndMessaging::{
+		DestroyWindow, DispatchMessageW, GetMessageW, PostMessageW, MSG, WM_CLIPBOARDUPDATE,
+		WM_CLOSE,
+	},
+};
+
+/// How many times the watcher tries to open the clipboard after a change, as other programs
+/// that were told about the same change may be reading it.
+const OPEN_ATTEMPTS: usize = 10;
+
+/// How long the watcher waits before trying to open the clipboard again.
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// Something that was on the clipboard at some point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+	/// When the contents were last seen on the clipboard.
+	pub timestamp: SystemTime,
+	pub content: ClipboardSnapshot,
+	hash: u64,
+}
+
+/// A bounded history of the clipboard's contents, recorded by a background thread.
+///
+/// The thread listens for clipboard changes with a window of its own, and opens the clipboard itself to record them.
+/// Windows only announces a change once whoever made it closes the clipboard, and the clipboard can't be opened
+/// by anyone else in the meantime, so nothing is recorded while a [`ClipboardHandle`] has it open,
+/// including ones in this program.
+///
+/// The newest entry comes first. Contents that ask not to be monitored,
+/// or not to be kept in the clipboard history, are never recorded.
+pub struct ClipboardHistory {
+	entries: Arc<Mutex<VecDeque<HistoryEntry>>>,
+	/// The watcher's window, which it stops listening with once it's closed.
+	window: HWND,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl ClipboardHistory {
+	/// The default limit on the size of a single entry, in bytes.
+	pub const DEFAULT_MAX_ENTRY_SIZE: usize = 16 * 1024 * 1024;
+
+	/// Starts recording up to `capacity` entries, dropping the oldest ones once it's full.
+	///
+	/// Whatever's on the clipboard already is recorded straight away.
+	pub fn start(capacity: usize) -> Result<Self> {
+		Self::start_with_max_entry_size(capacity, Self::DEFAULT_MAX_ENTRY_SIZE)
+	}
+
+	/// Like [`ClipboardHistory::start`], but skips any contents bigger than `max_entry_size` bytes,
+	/// instead of the default limit.
+	pub fn start_with_max_entry_size(capacity: usize, max_entry_size: usize) -> Result<Self> {
+		let entries = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+		let (ready, wait_ready) = mpsc::channel();
+		let thread = std::thread::Builder::new()
+			.name("clipee history".to_owned())
+			.spawn({
+				let entries = entries.clone();
+				move || {
+					let window = match listen() {
+						Ok(window) => window,
+						Err(err) => {
+							let _ = ready.send(Err(err));
+							return;
+						}
+					};
+					let _ = ready.send(Ok(window));
+					watch(window, &entries, capacity, max_entry_size);
+				}
+			})
+			.map_err(|err| Error::SpawnThread(err.to_string()))?;
+		let window = match wait_ready.recv() {
+			Ok(Ok(window)) => window,
+			// The thread has finished by now, as it only stops early if it couldn't start listening.
+			Ok(Err(err)) => {
+				let _ = thread.join();
+				return Err(err);
+			}
+			Err(_) => {
+				let _ = thread.join();
+				return Err(Error::SpawnThread(
+					"the history thread stopped before it started listening".to_owned(),
+				));
+			}
+		};
+		Ok(Self {
+			entries,
+			window,
+			thread: Some(thread),
+		})
+	}
+
+	/// Returns every recorded entry, newest first.
+	pub fn entries(&self) -> Vec<HistoryEntry> {
+		self.entries.lock().iter().cloned().collect()
+	}
+
+	/// Returns the entry at the given index, where 0 is the newest.
+	pub fn get(&self, idx: usize) -> Option<HistoryEntry> {
+		self.entries.lock().get(idx).cloned()
+	}
+
+	/// Puts the entry at the given index back on the clipboard, and moves it to the front of the history,
+	/// returning `false` if there's no such entry.
+	///
+	/// The entry is put on the clipboard with [`ClipboardHandle::new`], so if there's a handle already,
+	/// this has to be called from the thread that created it.
+	pub fn restore(&self, idx: usize) -> Result<bool> {
+		let entry = match self.get(idx) {
+			Some(entry) => entry,
+			None => return Ok(false),
+		};
+		let handle = ClipboardHandle::new()?;
+		handle.restore_snapshot(&entry.content)?;
+		// The watcher hears about this once the clipboard is closed, and with the entry at the front by then,
+		// it sees the same contents as the newest entry, rather than recording them again.
+		let mut entries = self.entries.lock();
+		if let Some(idx) = entries.iter().position(|other| other.hash == entry.hash) {
+			if let Some(mut entry) = entries.remove(idx) {
+				entry.timestamp = SystemTime::now();
+				entries.push_front(entry);
+			}
+		}
+		Ok(true)
+	}
+
+	/// Forgets every recorded entry.
+	pub fn clear(&self) {
+		self.entries.lock().clear();
+	}
+}
+
+impl Drop for ClipboardHistory {
+	fn drop(&mut self) {
+		// If this fails, the window is already gone, and so is the thread.
+		unsafe { PostMessageW(self.window, WM_CLOSE, WPARAM(0), LPARAM(0)) };
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Creates a window on this thread, and has Windows tell it whenever the clipboard changes.
+fn listen() -> Result<HWND> {
+	let window = window::create()?;
+	if !unsafe { AddClipboardFormatListener(window) }.as_bool() {
+		let err = WindowsError::from_last_error();
+		unsafe { DestroyWindow(window) };
+		return Err(Error::AddListener(err));
+	}
+	Ok(window)
+}
+
+/// Records the clipboard's contents, then again whenever they change, until the window is closed.
+fn watch(
+	window: HWND,
+	entries: &Mutex<VecDeque<HistoryEntry>>,
+	capacity: usize,
+	max_entry_size: usize,
+) {
+	record_change(window, entries, capacity, max_entry_size);
+	let mut message = MSG::default();
+	// GetMessageW returns -1 if it fails, which there's nothing to be done about other than stopping.
+	while unsafe { GetMessageW(&mut message, HWND::default(), 0, 0) }.0 > 0 {
+		match message.message {
+			WM_CLIPBOARDUPDATE => record_change(window, entries, capacity, max_entry_size),
+			WM_CLOSE if message.hwnd == window => break,
+			_ => {
+				unsafe { DispatchMessageW(&message) };
+			}
+		}
+	}
+	unsafe {
+		RemoveClipboardFormatListener(window);
+		DestroyWindow(window);
+	}
+}
+
+/// Opens the clipboard on this thread, and records what's on it.
+fn record_change(
+	window: HWND,
+	entries: &Mutex<VecDeque<HistoryEntry>>,
+	capacity: usize,
+	max_entry_size: usize,
+) {
+	// A failed capture is just a missed entry; the next change will be tried again.
+	match open(window).and_then(|handle| capture(&handle, max_entry_size)) {
+		Ok(Some(entry)) => {
+			trace_event!(debug, "recording clipboard change");
+			record(&mut entries.lock(), entry, capacity);
+		}
+		Ok(None) => {
+			trace_event!(debug, "skipping clipboard change");
+		}
+		Err(_err) => {
+			trace_event!(warn, error = %_err, "failed to capture clipboard change");
+		}
+	}
+}
+
+/// Opens the clipboard with the watcher's window, until the returned handle is dropped.
+fn open(window: HWND) -> Result<ClipboardHandleInner> {
+	let mut attempts = 1;
+	loop {
+		match ClipboardHandleInner::open_with(window, ClipboardOptions::default()) {
+			Err(Error::OpenClipboard(_)) if attempts < OPEN_ATTEMPTS => {
+				attempts += 1;
+				std::thread::sleep(OPEN_RETRY_DELAY);
+			}
+			result => return result,
+		}
+	}
+}
+
+/// Snapshots the clipboard, unless its contents opt out of being recorded or are too big.
+fn capture(handle: &ClipboardHandleInner, max_entry_size: usize) -> Result<Option<HistoryEntry>> {
+	let exclude = format::ClipboardFormat::register(
+		format::registered::EXCLUDE_CLIPBOARD_CONTENT_FROM_MONITOR_PROCESSING,
+	)?;
+	if handle.available_formats()?.contains(&exclude) || handle.history_allowed()? == Some(false) {
+		return Ok(None);
+	}
+	let content = match ClipboardSnapshot::capture(handle, Some(max_entry_size))? {
+		Some(content) if !content.is_empty() => content,
+		_ => return Ok(None),
+	};
+	let mut hasher = DefaultHasher::new();
+	content.hash(&mut hasher);
+	Ok(Some(HistoryEntry {
+		timestamp: SystemTime::now(),
+		content,
+		hash: hasher.finish(),
+	}))
+}
+
+/// Adds an entry to the front of the history, unless it's the same as the newest one.
+fn record(entries: &mut VecDeque<HistoryEntry>, entry: HistoryEntry, capacity: usize) {
+	if capacity == 0 || entries.front().map(|newest| newest.hash) == Some(entry.hash) {
+		return;
+	}
+	entries.truncate(capacity - 1);
+	entries.push_front(entry);
+}
@@ -1,66 +1,172 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+/// Emits a `tracing` event when the `tracing` feature is enabled, and compiles to nothing otherwise.
+macro_rules! trace_event {
+	($level:ident, $($arg:tt)+) => {{
+		#[cfg(feature = "tracing")]
+		tracing::$level!($($arg)+);
+	}};
+}
+
+pub mod builder;
 pub mod error;
+pub mod exclusive;
 pub mod format;
+pub(crate) mod handle;
+pub mod history;
 pub(crate) mod lock;
+pub mod policy;
+pub mod session;
+pub mod snapshot;
+pub(crate) mod window;
 
 use self::{
+	builder::{ClipboardHandleBuilder, ClipboardOptions},
 	error::{Error, Result, WindowsError},
-	format::ClipboardFormat,
+	format::{
+		files::SetFilesOptions,
+		string::{LossyString, NulPolicy},
+		ClipboardFormat, FormatInfo, FormatIter,
+	},
+	handle::{ClipboardData, HGlobal},
 	lock::LockedPtr,
+	policy::{CloudPolicy, HistoryPolicy},
+	session::ClipboardSession,
+	snapshot::ClipboardSnapshot,
 };
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use std::{
+	ffi::{OsStr, OsString},
+	hash::Hasher,
 	ops::Deref,
+	os::windows::ffi::OsStrExt,
 	path::{Path, PathBuf},
-	sync::{Arc, Weak},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Weak,
+	},
+	time::Duration,
 };
-use windows::{
-	core::PCWSTR,
-	Win32::{
-		Foundation::{BOOL, HANDLE, HINSTANCE, HWND, POINT},
-		Graphics::Gdi::{BITMAPINFO, HBITMAP},
-		System::DataExchange::{
-			CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData,
+use twox_hash::XxHash64;
+#[cfg(feature = "image")]
+use windows::Win32::Graphics::Gdi::BITMAPINFO;
+use windows::Win32::{
+	Foundation::{SetLastError, HANDLE, HWND, LPARAM, NO_ERROR, WPARAM},
+	Graphics::Gdi::{DeleteEnhMetaFile, GetObjectW, BITMAP, HBITMAP, HENHMETAFILE},
+	System::{
+		DataExchange::{
+			CloseClipboard, EmptyClipboard, GetClipboardData, GetClipboardOwner,
+			GetClipboardSequenceNumber, GetOpenClipboardWindow, GetUpdatedClipboardFormats,
 			IsClipboardFormatAvailable, OpenClipboard, SetClipboardData,
 		},
-		UI::{
-			Shell::DROPFILES,
-			WindowsAndMessaging::{
-				CloseWindow, CreateWindowExW, HMENU, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE,
-			},
-		},
+		Threading::GetCurrentThreadId,
+	},
+	UI::WindowsAndMessaging::{
+		DestroyWindow, DispatchMessageW, GetWindowThreadProcessId, IsWindow, PeekMessageW,
+		SendMessageTimeoutW, MSG, PM_REMOVE, SMTO_ABORTIFHUNG, WM_NULL,
 	},
 };
 
-static CLIPBOARD_HANDLE: OnceCell<Mutex<Weak<ClipboardHandleInner>>> = OnceCell::new();
+static CLIPBOARD_HANDLE: OnceCell<Mutex<Weak<OpenedClipboard>>> = OnceCell::new();
 
 #[derive(Clone)]
-pub struct ClipboardHandle(Arc<ClipboardHandleInner>);
+pub struct ClipboardHandle(ClipboardHandleInner);
 
 impl ClipboardHandle {
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", name = "ClipboardHandle::new")
+	)]
 	pub fn new() -> Result<Self> {
-		match CLIPBOARD_HANDLE.get() {
-			Some(handle) => {
-				let mut handle = handle.lock();
-				match handle.upgrade() {
-					Some(handle) => Ok(ClipboardHandle(handle)),
-					None => {
-						let new_handle = Arc::new(ClipboardHandleInner::new()?);
-						*handle = Arc::downgrade(&new_handle);
-						Ok(ClipboardHandle(new_handle))
-					}
-				}
-			}
+		Self::get_or_create(ClipboardOptions::default())
+	}
+
+	/// Opens the clipboard with a window of the caller's, instead of a hidden window of our own.
+	///
+	/// The window isn't destroyed when the handle is dropped, and as clipboard messages are sent to it
+	/// rather than to us, [`ClipboardHandleInner::set_delayed`] and [`ClipboardHandleInner::on_ownership_lost`]
+	/// only work if its window procedure forwards them.
+	/// Fails with [`Error::InvalidWindow`] if `window` isn't a window,
+	/// or with [`Error::ClipboardAlreadyOpen`] if a handle with another window is still alive.
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", name = "ClipboardHandle::with_window")
+	)]
+	pub fn with_window(window: isize) -> Result<Self> {
+		let window = HWND(window);
+		if !unsafe { IsWindow(window) }.as_bool() {
+			return Err(Error::InvalidWindow(window.0));
+		}
+		let mut handle = CLIPBOARD_HANDLE
+			.get_or_init(|| Mutex::new(Weak::new()))
+			.lock();
+		let clipboard = match handle.upgrade() {
+			Some(clipboard) if clipboard.window == window => clipboard,
+			Some(_) => return Err(Error::ClipboardAlreadyOpen),
 			None => {
-				let handle = Arc::new(ClipboardHandleInner::new()?);
-				CLIPBOARD_HANDLE
-					.set(Mutex::new(Arc::downgrade(&handle)))
-					.unwrap_or_else(|_| unreachable!());
-				Ok(ClipboardHandle(handle))
+				let clipboard = Arc::new(OpenedClipboard::with_window(window)?);
+				*handle = Arc::downgrade(&clipboard);
+				clipboard
 			}
+		};
+		Ok(Self::from_clipboard(clipboard, ClipboardOptions::default()))
+	}
+
+	/// Returns a builder for configuring the clipboard handle.
+	pub fn builder() -> ClipboardHandleBuilder {
+		ClipboardHandleBuilder::default()
+	}
+
+	/// Tears down this handle and opens the clipboard again, for when it's no longer [valid](ClipboardHandleInner::is_valid).
+	///
+	/// Other clones keep the old handle, but [`ClipboardHandle::new`] returns the new one from now on.
+	/// A handle made with [`ClipboardHandle::with_window`] is reopened with the same window,
+	/// and fails with [`Error::InvalidWindow`] if that window is gone.
+	/// This handle's options are kept.
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", name = "ClipboardHandle::refresh", skip_all)
+	)]
+	pub fn refresh(&mut self) -> Result<()> {
+		let mut shared = CLIPBOARD_HANDLE
+			.get_or_init(|| Mutex::new(Weak::new()))
+			.lock();
+		let old = &self.0.clipboard;
+		if !old.owns_window && !unsafe { IsWindow(old.window) }.as_bool() {
+			return Err(Error::InvalidWindow(old.window.0));
 		}
+		old.retire();
+		let clipboard = Arc::new(if old.owns_window {
+			OpenedClipboard::new()?
+		} else {
+			OpenedClipboard::with_window(old.window)?
+		});
+		*shared = Arc::downgrade(&clipboard);
+		self.0.clipboard = clipboard;
+		Ok(())
+	}
+
+	/// Returns a handle to the shared clipboard, opening it if it isn't open yet.
+	///
+	/// The options only apply to the returned handle and its clones, never to any other handle.
+	pub(crate) fn get_or_create(options: ClipboardOptions) -> Result<Self> {
+		let mut handle = CLIPBOARD_HANDLE
+			.get_or_init(|| Mutex::new(Weak::new()))
+			.lock();
+		let clipboard = match handle.upgrade() {
+			Some(clipboard) => clipboard,
+			None => {
+				let clipboard = Arc::new(OpenedClipboard::new()?);
+				*handle = Arc::downgrade(&clipboard);
+				clipboard
+			}
+		};
+		Ok(Self::from_clipboard(clipboard, options))
+	}
+
+	fn from_clipboard(clipboard: Arc<OpenedClipboard>, options: ClipboardOptions) -> Self {
+		Self(ClipboardHandleInner { clipboard, options })
 	}
 }
 
@@ -80,197 +186,989 @@ impl AsRef<ClipboardHandleInner> for ClipboardHandle {
 
 /// This is just a type that runs `OpenClipboard` on creation, and `CloseClipboard` on drop.
 /// It's used to ensure that the clipboard is always closed when we're done with it.
+///
+/// There's only ever one of these at a time, shared by every handle.
 #[derive(Debug)]
-pub struct ClipboardHandleInner {
+struct OpenedClipboard {
 	window: HWND,
+	/// Whether we created the window, and so have to destroy it.
+	owns_window: bool,
+	/// Whether a [`ClipboardSession`] is currently active.
+	in_session: AtomicBool,
+	/// Whether [`ClipboardHandle::refresh`] has replaced this, so dropping it mustn't touch the clipboard.
+	retired: AtomicBool,
 }
 
-impl ClipboardHandleInner {
+impl OpenedClipboard {
 	fn new() -> Result<Self> {
-		static STYLE: &[u16] = &[0x53, 0x74, 0x61, 0x74, 0x69, 0x63, 0x00]; // "Static" + \0
-		let window = unsafe {
-			CreateWindowExW(
-				WINDOW_EX_STYLE::default(),
-				PCWSTR(STYLE.as_ptr()),
-				PCWSTR::default(),
-				WINDOW_STYLE::default(),
-				0,
-				0,
-				0,
-				0,
-				HWND_MESSAGE,
-				HMENU::default(),
-				HINSTANCE::default(),
-				std::ptr::null(),
-			)
-		};
-		if window.is_invalid() {
-			return Err(Error::CreateWindow(WindowsError::from_last_error()));
-		};
+		let window = window::create()?;
+		if !unsafe { OpenClipboard(window) }.as_bool() {
+			let err = WindowsError::from_last_error();
+			trace_event!(warn, error = err.code(), "failed to open clipboard");
+			unsafe { DestroyWindow(window) };
+			return Err(Error::OpenClipboard(err));
+		}
+		trace_event!(debug, window = window.0, "opened clipboard");
+		Ok(Self {
+			window,
+			owns_window: true,
+			in_session: AtomicBool::new(false),
+			retired: AtomicBool::new(false),
+		})
+	}
+
+	fn with_window(window: HWND) -> Result<Self> {
 		if !unsafe { OpenClipboard(window) }.as_bool() {
-			return Err(Error::OpenClipboard(WindowsError::from_last_error()));
+			let err = WindowsError::from_last_error();
+			trace_event!(warn, error = err.code(), "failed to open clipboard");
+			return Err(Error::OpenClipboard(err));
+		}
+		trace_event!(
+			debug,
+			window = window.0,
+			"opened clipboard with caller's window"
+		);
+		Ok(Self {
+			window,
+			owns_window: false,
+			in_session: AtomicBool::new(false),
+			retired: AtomicBool::new(false),
+		})
+	}
+
+	/// Closes the clipboard and forgets any promised formats, before [`ClipboardHandle::refresh`] replaces this.
+	fn retire(&self) {
+		trace_event!(debug, window = self.window.0, "retiring clipboard handle");
+		self.retired.store(true, Ordering::SeqCst);
+		// Fails harmlessly if the clipboard was already closed.
+		unsafe { CloseClipboard() };
+		if self.owns_window {
+			window::reset();
+		}
+	}
+}
+
+impl Drop for OpenedClipboard {
+	fn drop(&mut self) {
+		// A retired handle was already closed, and the clipboard may be open with its replacement by now.
+		if self.retired.load(Ordering::SeqCst) {
+			if self.owns_window {
+				unsafe { DestroyWindow(self.window) };
+			}
+			return;
+		}
+		trace_event!(debug, "closing clipboard");
+		unsafe { CloseClipboard() };
+		// Someone else's window is theirs to destroy.
+		if self.owns_window {
+			// This sends WM_RENDERALLFORMATS if we still own the clipboard, so promised formats are rendered.
+			unsafe { DestroyWindow(self.window) };
+			window::reset();
 		}
-		Ok(Self { window })
 	}
+}
+
+/// A handle to the clipboard, which is shared with every other handle, along with options of its own.
+#[derive(Debug, Clone)]
+pub struct ClipboardHandleInner {
+	clipboard: Arc<OpenedClipboard>,
+	/// Copied into clones of this handle, but never shared with handles made some other way.
+	options: ClipboardOptions,
+}
 
+impl ClipboardHandleInner {
+	/// Opens the clipboard with a window on this thread, until the returned handle is dropped,
+	/// without it becoming the shared handle that [`ClipboardHandle::new`] returns.
+	pub(crate) fn open_with(window: HWND, options: ClipboardOptions) -> Result<Self> {
+		Ok(Self {
+			clipboard: Arc::new(OpenedClipboard::with_window(window)?),
+			options,
+		})
+	}
+
+	/// Returns the window that the clipboard is opened with.
+	pub fn window(&self) -> HWND {
+		self.clipboard.window
+	}
+
+	/// Returns whether the clipboard is still open with this handle's window.
+	///
+	/// This stops being the case if the window is destroyed, or the clipboard is closed behind our back,
+	/// after which every operation fails until the handle is [refreshed](ClipboardHandle::refresh).
+	pub fn is_valid(&self) -> bool {
+		let window = self.window();
+		!self.clipboard.retired.load(Ordering::SeqCst)
+			&& unsafe { IsWindow(window) }.as_bool()
+			&& unsafe { GetOpenClipboardWindow() } == window
+	}
+
+	/// Starts a session, which groups reads and writes together, and only writes when it's committed.
+	///
+	/// Fails with [`Error::ClipboardAlreadyOpen`] if there's already an active session.
+	pub fn session(&self) -> Result<ClipboardSession<'_>> {
+		ClipboardSession::new(self)
+	}
+
+	/// Returns the maximum number of bytes that will be read from a single clipboard format, if there is a limit.
+	pub fn max_read_size(&self) -> Option<usize> {
+		self.options.max_read_size
+	}
+
+	/// Returns how long reads will wait for the clipboard's owner to provide the data, if there is a limit.
+	pub fn read_timeout(&self) -> Option<Duration> {
+		self.options.read_timeout
+	}
+
+	/// Replaces the clipboard's contents with a string.
+	///
+	/// Like every setter other than [`Self::set_raw`], this empties the clipboard first,
+	/// which also makes us the owner of its contents until something else replaces them.
+	/// Fails with [`Error::InteriorNul`] if the string has a NUL character in it,
+	/// see [`Self::set_string_with_nul_policy`] to do something else instead.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub fn set_string<StringType: ToString>(&self, string: StringType) -> Result<()> {
-		self.set_string_impl(string.to_string())
+		self.set_string_impl(&string.to_string(), NulPolicy::default())
 	}
 
-	fn set_string_impl(&self, string: String) -> Result<()> {
-		// Encode string as UTF-16
-		let utf16_bytes = string.encode_utf16().collect::<Vec<_>>();
-		// Get number of 16-bit words in this string.
-		let memory_len = utf16_bytes.len();
+	/// Like [`Self::set_string`], but with a choice of what to do if the string has a NUL character in it.
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", skip(self, string))
+	)]
+	pub fn set_string_with_nul_policy<StringType: ToString>(
+		&self,
+		string: StringType,
+		policy: NulPolicy,
+	) -> Result<()> {
+		self.set_string_impl(&string.to_string(), policy)
+	}
+
+	fn set_string_impl(&self, string: &str, policy: NulPolicy) -> Result<()> {
+		self.set_wide_string(policy.apply(string)?.encode_utf16())
+	}
+
+	/// Replaces the clipboard's contents with an OS string, which is written exactly as it is,
+	/// even if it isn't valid Unicode.
+	///
+	/// Like [`Self::set_string`], this fails with [`Error::InteriorNul`] if the string has a NUL in it,
+	/// see [`Self::set_string_os_with_nul_policy`] to do something else instead.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_string_os<S: AsRef<OsStr>>(&self, string: S) -> Result<()> {
+		self.set_string_os_with_nul_policy(string, NulPolicy::default())
+	}
+
+	/// Like [`Self::set_string_os`], but with a choice of what to do if the string has a NUL in it.
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", skip(self, string))
+	)]
+	pub fn set_string_os_with_nul_policy<S: AsRef<OsStr>>(
+		&self,
+		string: S,
+		policy: NulPolicy,
+	) -> Result<()> {
+		let wide = string.as_ref().encode_wide().collect::<Vec<u16>>();
+		self.set_wide_string(policy.apply_wide(&wide)?.iter().copied())
+	}
+
+	/// Puts UTF-16 code units on the clipboard as `CF_UNICODETEXT`.
+	fn set_wide_string<I: Iterator<Item = u16> + Clone>(&self, string: I) -> Result<()> {
+		// Figure out how many UTF-16 code units this string will take up.
+		let memory_len = string.clone().count();
 		// Allocate memory for this string (+ null terminator)
-		let memory = LockedPtr::<u16>::alloc(memory_len + 1)?;
-		// Convert that memory slice into a &mut [u16]
-		let slice = unsafe { std::slice::from_raw_parts_mut(memory.as_mut_ptr(), memory_len + 1) };
-		// Copy the UTF-16 bytes into the slice
-		slice[..memory_len].copy_from_slice(&utf16_bytes[..]);
-		// Set last byte to a null byte
-		slice[memory_len] = 0;
-		// Alright, time to set this string on the clipboard
-		if unsafe { SetClipboardData(ClipboardFormat::UnicodeText.into(), memory.as_raw_handle()) }
-			.is_invalid()
-		{
-			return Err(Error::SetClipboard(WindowsError::from_last_error()));
+		let mut memory = LockedPtr::<u16>::alloc_zeroed(lock::checked_len(memory_len, 1)?)?;
+		let slice = memory.as_mut_slice()?;
+		// Encode the string as UTF-16 straight into global memory.
+		// The allocation is zeroed, so the null terminator is already there.
+		for (dst, src) in slice.iter_mut().zip(string) {
+			*dst = src;
 		}
-		Ok(())
+		// Alright, time to set this string on the clipboard
+		self.replace_with_memory(ClipboardFormat::UnicodeText, memory)
 	}
 
+	/// Puts a string on the clipboard, marked so that it's kept out of clipboard history,
+	/// cloud clipboard sync, and clipboard monitors.
+	///
+	/// This is intended for secrets, such as passwords.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_string_sensitive<StringType: ToString>(&self, string: StringType) -> Result<()> {
+		self.set_string_impl(&string.to_string(), NulPolicy::default())?;
+		self.set_raw(
+			ClipboardFormat::register(
+				format::registered::EXCLUDE_CLIPBOARD_CONTENT_FROM_MONITOR_PROCESSING,
+			)?,
+			&0_u32.to_le_bytes(),
+		)?;
+		self.set_history_policy(HistoryPolicy::Deny)?;
+		self.set_cloud_policy(CloudPolicy::Deny)
+	}
+
+	/// Gets the `CF_TEXT` on the clipboard, which has to be valid UTF-8.
+	///
+	/// Text from programs using a legacy code page usually isn't, in which case this fails with
+	/// [`Error::InvalidString`]; use [`Self::string_lossy`] or [`Self::string_unicode`] to read it anyway.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub fn string(&self) -> Result<Option<String>> {
-		if !Self::is_clipboard_format_available(ClipboardFormat::Text) {
-			return Ok(None);
-		}
-		let handle = Self::get_clipboard_data(ClipboardFormat::Text)?;
-		format::string::get(handle).map(Some)
+		self.read(ClipboardFormat::Text, format::string::get)
+	}
+
+	/// Gets the `CF_TEXT` on the clipboard, replacing anything that isn't valid UTF-8,
+	/// along with the original bytes.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn string_lossy(&self) -> Result<Option<LossyString>> {
+		self.read(ClipboardFormat::Text, format::string::get_lossy)
 	}
 
+	/// Gets the `CF_UNICODETEXT` on the clipboard.
+	///
+	/// A byte order mark at the start, which some programs add, is removed; use [`Self::string_unicode_raw`] to keep it.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub fn string_unicode(&self) -> Result<Option<String>> {
-		if !Self::is_clipboard_format_available(ClipboardFormat::UnicodeText) {
-			return Ok(None);
-		}
-		let handle = Self::get_clipboard_data(ClipboardFormat::UnicodeText)?;
-		format::string::get_unicode(handle).map(Some)
+		self.read(ClipboardFormat::UnicodeText, format::string::get_unicode)
 	}
 
+	/// Like [`Self::string_unicode`], but keeps the byte order mark if the text starts with one.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn string_unicode_raw(&self) -> Result<Option<String>> {
+		self.read(
+			ClipboardFormat::UnicodeText,
+			format::string::get_unicode_raw,
+		)
+	}
+
+	/// Gets the text on the clipboard exactly as it is, without replacing unpaired surrogates
+	/// like [`Self::string_unicode`] does.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn string_unicode_os(&self) -> Result<Option<OsString>> {
+		self.read(ClipboardFormat::UnicodeText, format::string::get_unicode_os)
+	}
+
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub fn files(&self) -> Result<Option<Vec<PathBuf>>> {
-		if !Self::is_clipboard_format_available(ClipboardFormat::DropHandle) {
-			return Ok(None);
+		self.read(ClipboardFormat::DropHandle, format::files::get)
+	}
+
+	/// Gets the text on the clipboard, falling back to the text of the HTML fragment if there's no plain text,
+	/// which happens with some programs that only put HTML on the clipboard.
+	///
+	/// The fallback is best-effort, see [`format::html::to_text`] for what it does and doesn't handle.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn text_any(&self) -> Result<Option<String>> {
+		if let Some(text) = self.string_unicode()? {
+			return Ok(Some(text));
 		}
-		let handle = Self::get_clipboard_data(ClipboardFormat::DropHandle)?;
-		format::files::get(handle).map(Some)
+		let html = ClipboardFormat::register(format::registered::HTML)?;
+		self.read(html, format::html::get_text)
 	}
 
-	pub fn set_files<PathType: AsRef<Path>, PathList: AsRef<[PathType]>>(
-		&self,
-		paths: PathList,
-	) -> Result<()> {
-		self.set_files_impl(paths.as_ref())
+	/// Gets the URL on the clipboard, from the registered URL formats rather than the text.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn url(&self) -> Result<Option<String>> {
+		let wide = ClipboardFormat::register(format::registered::URL_WIDE)?;
+		if let Some(url) = self.read(wide, format::string::get_unicode)? {
+			return Ok(Some(url));
+		}
+		let ansi = ClipboardFormat::register(format::registered::URL)?;
+		self.read(ansi, format::string::get)
 	}
 
-	fn set_files_impl<PathType: AsRef<Path>>(&self, paths: &[PathType]) -> Result<()> {
-		let mut list = Vec::<u16>::new();
-		for path in paths {
-			let path = match path.as_ref().to_str() {
-				Some(s) => s,
-				None => continue,
-			};
-			// Reserve enough bytes for the UTF-16 version of this path, and the null byte.
-			list.reserve((path.len() * 2) + std::mem::size_of::<u16>());
-			path.encode_utf16().for_each(|byte| list.push(byte));
-			// Null-terminate it.
-			list.push(0);
+	/// Replaces the clipboard's contents with a URL, so that it's pasted as a link by programs that
+	/// support it, and as text by everything else.
+	///
+	/// The URL isn't validated, other than not being empty. Anything in it that isn't ASCII is
+	/// percent-encoded in the ANSI URL format, which can't hold it otherwise.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_url(&self, url: &str) -> Result<()> {
+		if url.is_empty() {
+			return Err(Error::EmptyUrl);
 		}
-		// The list is double-null-terminated, so we need a SECOND null terminator here!
-		list.push(0);
-		self.set_files_impl_2(list)
+		let wide_format = ClipboardFormat::register(format::registered::URL_WIDE)?;
+		let ansi_format = ClipboardFormat::register(format::registered::URL)?;
+		self.set_string(url)?;
+		let wide =
+			LockedPtr::<u16>::alloc_from_slice_with_nul(&url.encode_utf16().collect::<Vec<u16>>())?;
+		Self::set_clipboard_memory(wide_format, wide)?;
+		let ansi =
+			LockedPtr::<u8>::alloc_from_slice_with_nul(format::string::ascii_url(url).as_bytes())?;
+		Self::set_clipboard_memory(ansi_format, ansi)
 	}
 
-	fn set_files_impl_2(&self, paths_structure: Vec<u16>) -> Result<()> {
-		let memory = LockedPtr::<u8>::alloc(
-			std::mem::size_of::<DROPFILES>() + (paths_structure.len() * std::mem::size_of::<u16>()),
-		)?;
-		// microsoft never intended anyone to manually create this but fuck you I do what I want.
-		let drop_files = DROPFILES {
-			pFiles: std::mem::size_of::<DROPFILES>() as u32,
-			pt: POINT::default(),
-			fNC: BOOL(1),
-			fWide: BOOL(1),
-		};
-		unsafe {
-			// this is a fucking abomination
-			*(memory.as_mut_ptr() as *mut DROPFILES) = drop_files;
-			// so is this
-			let u16_ptr = memory.as_mut_ptr().add(std::mem::size_of::<DROPFILES>()) as *mut u16;
-			// ugh let's just copy the UTF-16 bytes over.
-			std::ptr::copy_nonoverlapping(paths_structure.as_ptr(), u16_ptr, paths_structure.len());
-			// actually set the clipboard data
-			if SetClipboardData(ClipboardFormat::DropHandle.into(), memory.as_raw_handle())
-				.is_invalid()
+	/// Counts the files on the clipboard, without getting any of their paths.
+	pub fn file_count(&self) -> Result<Option<usize>> {
+		self.read(ClipboardFormat::DropHandle, format::files::count)
+	}
+
+	/// Gets the path of a single file on the clipboard, or `None` if there aren't that many files.
+	pub fn nth_file(&self, idx: usize) -> Result<Option<PathBuf>> {
+		self.read(ClipboardFormat::DropHandle, |handle| {
+			format::files::nth(handle, idx)
+		})
+		.map(Option::flatten)
+	}
+
+	/// Replaces the clipboard's contents with a list of files.
+	///
+	/// Paths are put on the clipboard as they are, even if they aren't valid Unicode. Empty paths are skipped,
+	/// and it fails with [`Error::NoFiles`] if there aren't any paths left.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_files<I>(&self, paths: I) -> Result<()>
+	where
+		I: IntoIterator,
+		I::Item: AsRef<Path>,
+	{
+		self.set_files_with_options(paths, &SetFilesOptions::default())
+	}
+
+	/// Like [`Self::set_files`], but with control over the `DROPFILES` header that the list starts with.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_files_with_options<I>(&self, paths: I, options: &SetFilesOptions) -> Result<()>
+	where
+		I: IntoIterator,
+		I::Item: AsRef<Path>,
+	{
+		let memory = format::files::alloc(paths, options)?;
+		self.replace_with_memory(ClipboardFormat::DropHandle, memory)
+	}
+
+	/// Gets the raw `CF_DIB` on the clipboard, header and all, for decoding with something other than the `image` crate.
+	///
+	/// Windows will synthesize this from any other bitmap format if needed.
+	pub fn dib_bytes(&self) -> Result<Option<Vec<u8>>> {
+		self.get_raw(ClipboardFormat::BitmapInfo)
+	}
+
+	#[cfg(feature = "image")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn image(&self) -> Result<Option<image::RgbImage>> {
+		let max_read_size = self.max_read_size();
+		self.reading(move || {
+			if !Self::is_clipboard_format_available(ClipboardFormat::Bitmap) {
+				return Ok(None);
+			}
+			let hbitmap = Self::get_clipboard_data::<HBITMAP>(ClipboardFormat::Bitmap)?;
+			// Windows offers a CF_DIB synthesized from any CF_BITMAP, but synthesizing it can still fail,
+			// such as for bitmaps that older programs made for a device that's gone now.
+			// The bitmap can still be read on its own then, just without a header describing it.
+			let bitmap_info = match Self::get_clipboard_data::<HGlobal>(ClipboardFormat::BitmapInfo)
+				.and_then(|memory| unsafe { LockedPtr::<BITMAPINFO>::new(memory) })
 			{
-				return Err(Error::SetClipboard(WindowsError::from_last_error()));
+				Ok(bitmap_info) => bitmap_info,
+				Err(_err) => {
+					trace_event!(debug, error = %_err, "no CF_DIB to go with CF_BITMAP, reading it on its own");
+					return format::bitmap::get_without_info(hbitmap, max_read_size).map(Some);
+				}
+			};
+			format::bitmap::get(hbitmap, bitmap_info, max_read_size).map(Some)
+		})
+	}
+
+	/// Gets the image on the clipboard, including its alpha channel.
+	///
+	/// This reads `CF_DIBV5`, which Windows will synthesize from any other bitmap format if needed.
+	#[cfg(feature = "image")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn image_rgba(&self) -> Result<Option<image::RgbaImage>> {
+		self.read(ClipboardFormat::BitmapV5, |handle| {
+			format::bitmap::get_rgba(&unsafe { LockedPtr::<u8>::new(handle) }?)
+		})
+	}
+
+	/// Gets the PNG file in the clipboard's registered `PNG` format, which some programs put there
+	/// alongside the bitmap formats, as it can be transparent.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn png(&self) -> Result<Option<Vec<u8>>> {
+		let format = ClipboardFormat::register(format::registered::PNG)?;
+		self.read(format, format::png::get)
+	}
+
+	/// Saves the image on the clipboard to a file, picking the file format from the extension of `path`.
+	///
+	/// When saving as PNG, the clipboard's own `PNG` format is written as-is if there is one,
+	/// which keeps its transparency and skips re-encoding the image.
+	/// Returns `None` if there's no image on the clipboard.
+	#[cfg(feature = "image")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn save_image<P: AsRef<Path>>(&self, path: P) -> Result<Option<()>> {
+		let path = path.as_ref();
+		let file_format = format::image_file::format_for_path(path)?;
+		if file_format == image::ImageFormat::Png {
+			if let Some(png) = self.png()? {
+				std::fs::write(path, png).map_err(|err| Error::SaveImage(err.into()))?;
+				return Ok(Some(()));
 			}
+		}
+		let image = match self.image()? {
+			Some(image) => image,
+			None => return Ok(None),
 		};
+		image
+			.save_with_format(path, file_format)
+			.map_err(|err| Error::SaveImage(err.into()))?;
+		Ok(Some(()))
+	}
+
+	/// Puts an image on the clipboard.
+	///
+	/// Images without an alpha channel (RGB and grayscale) are written as a 24-bit `CF_DIB`.
+	/// Images with an alpha channel (RGBA and grayscale with alpha) are written with [`Self::set_image_rgba`],
+	/// so that transparency is preserved for programs that support it.
+	#[cfg(feature = "image")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_image<ImageType: Into<image::DynamicImage>>(&self, image: ImageType) -> Result<()> {
+		let image = image.into();
+		if image.color().has_alpha() {
+			self.set_image_rgba(&image.into_rgba8())
+		} else {
+			let memory = format::bitmap::dib_from_rgb(&image.into_rgb8())?;
+			self.replace_with_memory(ClipboardFormat::BitmapInfo, memory)
+		}
+	}
+
+	/// Loads an image file and puts it on the clipboard.
+	///
+	/// The image is put on the clipboard as with [`Self::set_image`]. PNG files are also put on the clipboard
+	/// as-is, in the registered `PNG` format, so that programs that support it get the original file.
+	#[cfg(feature = "image")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_image_from_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+		let bytes = std::fs::read(path).map_err(|err| Error::OpenImage(err.into()))?;
+		let file_format =
+			image::guess_format(&bytes).map_err(|err| Error::DecodeImage(err.into()))?;
+		let image = image::load_from_memory_with_format(&bytes, file_format)
+			.map_err(|err| Error::DecodeImage(err.into()))?;
+		self.set_image(image)?;
+		if file_format == image::ImageFormat::Png {
+			self.set_raw(ClipboardFormat::register(format::registered::PNG)?, &bytes)?;
+		}
 		Ok(())
 	}
 
-	pub fn image(&self) -> Result<Option<image::RgbImage>> {
-		if !Self::is_clipboard_format_available(ClipboardFormat::Bitmap)
-			|| !Self::is_clipboard_format_available(ClipboardFormat::BitmapInfo)
+	/// Puts an image with an alpha channel on the clipboard.
+	///
+	/// The image is written as a 32-bit `CF_DIBV5` with an alpha channel, alongside a 24-bit `CF_DIB`
+	/// with the image flattened onto a white background, for programs that don't understand `CF_DIBV5`.
+	#[cfg(feature = "image")]
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_image_rgba(&self, image: &image::RgbaImage) -> Result<()> {
+		let flattened = format::bitmap::dib_from_rgb(&format::bitmap::flatten_onto_white(image))?;
+		let with_alpha = format::bitmap::dibv5_from_rgba(image)?;
+		// Windows synthesizes CF_BITMAP from whichever DIB was placed first, so the flattened one goes first.
+		if let Err(err) = self.replace_with_memory(ClipboardFormat::BitmapInfo, flattened) {
+			with_alpha.free();
+			return Err(err);
+		}
+		Self::set_clipboard_memory(ClipboardFormat::BitmapV5, with_alpha)
+	}
+
+	/// Gets the image on the clipboard as an [`arboard::ImageData`], like [`Self::image_rgba`].
+	#[cfg(feature = "arboard-compat")]
+	pub fn image_arboard(&self) -> Result<Option<arboard::ImageData<'static>>> {
+		Ok(self
+			.image_rgba()?
+			.map(format::arboard_compat::to_image_data))
+	}
+
+	/// Puts an [`arboard::ImageData`] on the clipboard, like [`Self::set_image_rgba`].
+	#[cfg(feature = "arboard-compat")]
+	pub fn set_image_arboard(&self, image: arboard::ImageData<'_>) -> Result<()> {
+		self.set_image_rgba(&format::arboard_compat::from_image_data(image)?)
+	}
+
+	/// Gets the enhanced metafile on the clipboard, as raw EMF bytes.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn enh_metafile(&self) -> Result<Option<Vec<u8>>> {
+		// This isn't global memory, so it's up to the metafile code to check its size.
+		self.reading(|| {
+			if !Self::is_clipboard_format_available(ClipboardFormat::EnhMetafile) {
+				return Ok(None);
+			}
+			let metafile = Self::get_clipboard_data::<HENHMETAFILE>(ClipboardFormat::EnhMetafile)?;
+			format::metafile::get(metafile, self.max_read_size()).map(Some)
+		})
+	}
+
+	/// Puts an enhanced metafile on the clipboard, from raw EMF bytes.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_enh_metafile(&self, bytes: &[u8]) -> Result<()> {
+		let metafile = format::metafile::create(bytes)?;
+		if let Err(err) = self.empty() {
+			unsafe { DeleteEnhMetaFile(metafile) };
+			return Err(err);
+		}
+		Self::set_clipboard_data(ClipboardFormat::EnhMetafile, metafile).inspect_err(|_| {
+			// The clipboard didn't take ownership, so we have to clean it up ourselves.
+			unsafe { DeleteEnhMetaFile(metafile) };
+		})
+	}
+
+	/// Gets the audio on the clipboard, as the bytes of a WAV file.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn wave(&self) -> Result<Option<Vec<u8>>> {
+		self.read(ClipboardFormat::Wave, format::wave::get)
+	}
+
+	/// Puts audio on the clipboard, from the bytes of a WAV file.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn set_wave(&self, wave: &[u8]) -> Result<()> {
+		if wave.len() < 12 || &wave[..4] != b"RIFF" || &wave[8..12] != b"WAVE" {
+			return Err(Error::InvalidWave);
+		}
+		self.empty()?;
+		self.set_raw(ClipboardFormat::Wave, wave)
+	}
+
+	/// Gets the raw bytes of a clipboard format.
+	///
+	/// This only works for formats stored in global memory, which is most of them,
+	/// but not handle-based formats such as [`ClipboardFormat::Bitmap`].
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", skip(self), fields(%format))
+	)]
+	pub fn get_raw(&self, format: ClipboardFormat) -> Result<Option<Vec<u8>>> {
+		self.read(format, |handle| {
+			let memory = unsafe { LockedPtr::<u8>::new(handle) }?;
+			Ok(memory.as_bytes()?.to_vec())
+		})
+	}
+
+	/// Puts raw bytes on the clipboard, as the given format.
+	///
+	/// Unlike the other setters, this doesn't empty the clipboard first, so it adds to what's already there;
+	/// call [`Self::empty`] first to replace it instead.
+	/// Empty data can't be put on the clipboard, and fails with [`Error::EmptyAllocation`].
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", skip(self, data), fields(%format, size = data.len()))
+	)]
+	pub fn set_raw(&self, format: ClipboardFormat, data: &[u8]) -> Result<()> {
+		let memory = LockedPtr::<u8>::alloc_from_slice(data)?;
+		Self::set_clipboard_memory(format, memory)
+	}
+
+	/// Returns the size in bytes of a format on the clipboard, without copying it,
+	/// or `None` if the clipboard doesn't have it.
+	///
+	/// For [`ClipboardFormat::Bitmap`], this is the size of the bitmap's pixels,
+	/// and for [`ClipboardFormat::EnhMetafile`], the size of its EMF data.
+	/// Formats that haven't been rendered yet are rendered to find out.
+	pub fn format_size(&self, format: ClipboardFormat) -> Result<Option<usize>> {
+		self.reading(|| {
+			if !Self::is_clipboard_format_available(format) {
+				return Ok(None);
+			}
+			match format {
+				ClipboardFormat::Bitmap => Self::bitmap_size(Self::get_clipboard_data(format)?),
+				ClipboardFormat::EnhMetafile => {
+					format::metafile::size(Self::get_clipboard_data(format)?)
+				}
+				_ => Ok(unsafe { Self::get_clipboard_data::<HGlobal>(format)?.size() }),
+			}
+			.map(Some)
+		})
+	}
+
+	/// Adds up the sizes of every format on the clipboard that's stored in global memory,
+	/// which is everything other than [`ClipboardFormat::Bitmap`] and [`ClipboardFormat::EnhMetafile`].
+	pub fn total_size(&self) -> Result<usize> {
+		let mut total = 0_usize;
+		for format in self.available_formats()? {
+			if matches!(
+				format,
+				ClipboardFormat::Bitmap | ClipboardFormat::EnhMetafile
+			) {
+				continue;
+			}
+			total = total.saturating_add(self.format_size(format)?.unwrap_or(0));
+		}
+		Ok(total)
+	}
+
+	/// Works out how many bytes a bitmap's pixels take up, from its dimensions.
+	fn bitmap_size(hbitmap: HBITMAP) -> Result<usize> {
+		let mut bitmap = BITMAP::default();
+		if unsafe {
+			GetObjectW(
+				hbitmap,
+				std::mem::size_of::<BITMAP>() as i32,
+				&mut bitmap as *mut BITMAP as *mut _,
+			)
+		} == 0
 		{
+			return Err(Error::BitmapObject(WindowsError::from_last_error()));
+		}
+		Ok(bitmap.bmWidthBytes.unsigned_abs() as usize * bitmap.bmHeight.unsigned_abs() as usize)
+	}
+
+	/// Copies everything on the clipboard, so it can be put back with [`ClipboardHandleInner::restore_snapshot`].
+	///
+	/// Fails if anything on the clipboard can't be read, see [`ClipboardSnapshot`] for what's left out on purpose.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn snapshot(&self) -> Result<ClipboardSnapshot> {
+		Ok(ClipboardSnapshot::capture(self, None)?.unwrap_or_default())
+	}
+
+	/// Replaces the clipboard's contents with a snapshot.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn restore_snapshot(&self, snapshot: &ClipboardSnapshot) -> Result<()> {
+		snapshot.restore(self)
+	}
+
+	/// Hashes everything on the clipboard, or returns `None` if it's empty.
+	///
+	/// The hash is the same for as long as the contents are, so it can be used to tell whether
+	/// they've actually changed, without copying them out of the clipboard.
+	/// It's XXH64 with a seed of 0, over the ID, length and data of each format in order of their IDs,
+	/// with the integers as little-endian bytes, so it's also the same across builds and Rust versions,
+	/// and can be stored.
+	///
+	/// Only the data itself is hashed, without whatever its allocation was rounded up with,
+	/// for the formats that say where their data ends: text, audio, and PNG files.
+	/// Everything else is hashed in full.
+	///
+	/// Fails with [`Error::PayloadTooLarge`] if any format is bigger than the maximum read size, as with any other read.
+	pub fn content_hash(&self) -> Result<Option<u64>> {
+		let mut formats = self.available_formats()?;
+		if formats.is_empty() {
 			return Ok(None);
 		}
-		let hbitmap = Self::get_clipboard_data(ClipboardFormat::Bitmap).map(|h| HBITMAP(h.0))?;
-		let bitmap_info = Self::get_clipboard_data(ClipboardFormat::BitmapInfo)
-			.and_then(|handle| unsafe { LockedPtr::<BITMAPINFO>::new(handle) })?;
-		format::bitmap::get(hbitmap, bitmap_info).map(Some)
+		// Formats are listed in the order they were set, which shouldn't matter.
+		formats.sort_by_key(|format| u32::from(*format));
+		let png = ClipboardFormat::register(format::registered::PNG)?;
+		let mut hasher = XxHash64::with_seed(0);
+		let mut hash_format = |format: ClipboardFormat, data: &[u8]| {
+			hasher.write(&u32::from(format).to_le_bytes());
+			hasher.write(&(data.len() as u64).to_le_bytes());
+			hasher.write(data);
+		};
+		for format in formats {
+			match format {
+				// Bitmaps are synthesized from CF_DIB and the other way around, so hashing the DIB covers both.
+				ClipboardFormat::Bitmap => continue,
+				ClipboardFormat::EnhMetafile => {
+					hash_format(format, &self.enh_metafile()?.unwrap_or_default());
+				}
+				_ => {
+					// The clipboard's been open since the formats were listed, so this can't have gone since.
+					self.read(format, |handle| {
+						let memory = unsafe { LockedPtr::<u8>::new(handle) }?;
+						hash_format(format, Self::payload(format, png, memory.as_bytes()?));
+						Ok(())
+					})?;
+				}
+			}
+		}
+		Ok(Some(hasher.finish()))
+	}
+
+	/// Cuts clipboard data off where the data itself ends, for the formats that say where that is.
+	fn payload(format: ClipboardFormat, png: ClipboardFormat, data: &[u8]) -> &[u8] {
+		let len = match format {
+			ClipboardFormat::Text => format::string::until_nul(data).len(),
+			ClipboardFormat::UnicodeText => data
+				.chunks_exact(2)
+				.position(|unit| unit == [0, 0])
+				.map_or(data.len(), |units| units * 2),
+			ClipboardFormat::Wave => format::wave::len(data),
+			format if format == png => format::png::len(data),
+			_ => data.len(),
+		};
+		&data[..len]
+	}
+
+	/// Promises to put a format on the clipboard, without producing its data until someone asks for it.
+	///
+	/// This only works once we own the clipboard, which means it has to have been emptied by us first.
+	/// The data is produced from the thread that created the clipboard handle, while it's processing messages
+	/// (see [`ClipboardHandleInner::process_messages`]), or when the handle is dropped, so that the promise is kept.
+	#[cfg_attr(
+		feature = "tracing",
+		tracing::instrument(level = "debug", skip(self, render), fields(%format))
+	)]
+	pub fn set_delayed<F: FnMut() -> Vec<u8> + Send + 'static>(
+		&self,
+		format: ClipboardFormat,
+		render: F,
+	) -> Result<()> {
+		window::promise(format, Box::new(render));
+		// Promising data is done by passing a null handle, which makes a null return value ambiguous.
+		unsafe { SetLastError(NO_ERROR) };
+		if unsafe { SetClipboardData(format.into(), HANDLE::default()) }.is_invalid() {
+			if let Some(err) = WindowsError::try_from_last_error() {
+				return Err(Error::SetClipboard { format, err });
+			}
+		}
+		Ok(())
+	}
+
+	/// Sets a callback for when something else takes over the clipboard, after we owned it.
+	///
+	/// Emptying the clipboard ourselves doesn't count, even though it makes us the owner again.
+	///
+	/// This is called from the thread that created the clipboard handle, while it's processing messages.
+	pub fn on_ownership_lost<F: Fn() + Send + Sync + 'static>(&self, callback: F) {
+		window::set_on_ownership_lost(Some(Arc::new(callback)));
+	}
+
+	/// Handles any messages that have been sent to the clipboard window, such as requests for promised formats.
+	///
+	/// Programs with their own message loop on the thread that created the clipboard handle don't need this.
+	pub fn process_messages(&self) {
+		let mut message = MSG::default();
+		while unsafe { PeekMessageW(&mut message, self.window(), 0, 0, PM_REMOVE) }.as_bool() {
+			unsafe { DispatchMessageW(&message) };
+		}
 	}
 
+	/// Returns the clipboard's sequence number, which changes whenever its contents do.
+	pub fn sequence_number(&self) -> u32 {
+		unsafe { GetClipboardSequenceNumber() }
+	}
+
+	/// Sets whether the current clipboard contents may be saved in the clipboard history.
+	///
+	/// This should be called after setting the contents, as it only applies to what's currently on the clipboard.
+	pub fn set_history_policy(&self, policy: HistoryPolicy) -> Result<()> {
+		self.set_raw(
+			ClipboardFormat::register(format::registered::CAN_INCLUDE_IN_CLIPBOARD_HISTORY)?,
+			&policy.to_dword().to_le_bytes(),
+		)
+	}
+
+	/// Sets whether the current clipboard contents may be synced to the cloud clipboard.
+	///
+	/// This should be called after setting the contents, as it only applies to what's currently on the clipboard.
+	pub fn set_cloud_policy(&self, policy: CloudPolicy) -> Result<()> {
+		self.set_raw(
+			ClipboardFormat::register(format::registered::CAN_UPLOAD_TO_CLOUD_CLIPBOARD)?,
+			&policy.to_dword().to_le_bytes(),
+		)
+	}
+
+	/// Returns whether the current clipboard contents may be saved in the clipboard history,
+	/// or `None` if the contents don't say, in which case the system default applies.
+	pub fn history_allowed(&self) -> Result<Option<bool>> {
+		let format =
+			ClipboardFormat::register(format::registered::CAN_INCLUDE_IN_CLIPBOARD_HISTORY)?;
+		Ok(self
+			.get_raw(format)?
+			.map(|data| policy::dword_allowed(&data)))
+	}
+
+	/// Returns whether the current clipboard contents may be synced to the cloud clipboard,
+	/// or `None` if the contents don't say, in which case the system default applies.
+	pub fn cloud_allowed(&self) -> Result<Option<bool>> {
+		let format = ClipboardFormat::register(format::registered::CAN_UPLOAD_TO_CLOUD_CLIPBOARD)?;
+		Ok(self
+			.get_raw(format)?
+			.map(|data| policy::dword_allowed(&data)))
+	}
+
+	/// Empties the clipboard, which makes us the owner of whatever's put on it next.
+	///
+	/// Any formats we promised with [`ClipboardHandleInner::set_delayed`] are forgotten,
+	/// but this doesn't count as losing the clipboard for [`ClipboardHandleInner::on_ownership_lost`].
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub fn empty(&self) -> Result<()> {
-		if !unsafe { EmptyClipboard() }.as_bool() {
-			return Err(Error::GetClipboard(WindowsError::from_last_error()));
+		if !window::empty_quietly(|| unsafe { EmptyClipboard() }).as_bool() {
+			return Err(Error::EmptyClipboard(WindowsError::from_last_error()));
 		}
 		Ok(())
 	}
 
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 	pub fn available_formats(&self) -> Result<Vec<ClipboardFormat>> {
-		let mut formats = Vec::<ClipboardFormat>::new();
-		let mut last_format = 0;
+		let formats = match Self::updated_formats() {
+			Some(formats) => formats,
+			None => {
+				trace_event!(
+					debug,
+					"GetUpdatedClipboardFormats failed, enumerating formats instead"
+				);
+				self.enumerate_formats()?
+			}
+		};
+		trace_event!(debug, count = formats.len(), "listed available formats");
+		Ok(formats)
+	}
+
+	/// Returns whether the clipboard has the given format.
+	pub fn has_format(&self, format: ClipboardFormat) -> Result<bool> {
+		self.has_any_format(&[format])
+	}
+
+	/// Returns whether the clipboard has any of the given formats.
+	pub fn has_any_format(&self, formats: &[ClipboardFormat]) -> Result<bool> {
+		Ok(self
+			.available_formats()?
+			.iter()
+			.any(|available| formats.contains(available)))
+	}
+
+	/// Lists the available formats with a single call to `GetUpdatedClipboardFormats`,
+	/// or returns `None` if that failed, in which case they have to be enumerated instead.
+	fn updated_formats() -> Option<Vec<ClipboardFormat>> {
+		let mut buffer = vec![0_u32; 32];
 		loop {
-			last_format = unsafe { EnumClipboardFormats(last_format) };
-			if last_format == 0 {
-				match WindowsError::try_from_last_error() {
-					Some(err) => return Err(Error::EnumClipboard(err)),
-					None => break,
-				}
+			let mut count = 0_u32;
+			if unsafe { GetUpdatedClipboardFormats(&mut buffer, &mut count) }.as_bool() {
+				buffer.truncate(count as usize);
+				return Some(
+					buffer
+						.into_iter()
+						.filter_map(ClipboardFormat::try_from_u32)
+						.collect(),
+				);
 			}
-			if let Some(format) = ClipboardFormat::try_from_u32(last_format) {
-				formats.push(format);
+			// The buffer was too small, and the count is how big it needs to be.
+			if count as usize > buffer.len() {
+				buffer.resize(count as usize, 0);
+				continue;
 			}
+			return None;
 		}
-		Ok(formats)
+	}
+
+	fn enumerate_formats(&self) -> Result<Vec<ClipboardFormat>> {
+		self.formats_iter()
+			.filter_map(|info| info.map(|info| info.format).transpose())
+			.collect()
+	}
+
+	/// Lazily enumerates the formats on the clipboard, including ones that don't have a [`ClipboardFormat`] variant.
+	///
+	/// Unlike [`ClipboardHandleInner::available_formats`], this can stop as soon as the wanted format is found.
+	pub fn formats_iter(&self) -> impl Iterator<Item = Result<FormatInfo>> + '_ {
+		FormatIter::new(self)
 	}
 
 	fn is_clipboard_format_available(format: ClipboardFormat) -> bool {
 		unsafe { IsClipboardFormatAvailable(format.into()) }.as_bool()
 	}
 
-	fn get_clipboard_data(format: ClipboardFormat) -> Result<HANDLE> {
-		let handle = unsafe { GetClipboardData(format.into()) };
-		if handle.is_invalid() {
-			return Err(Error::GetClipboard(WindowsError::from_last_error()));
+	/// Reads a format from the clipboard and decodes it, or returns `None` if the clipboard doesn't have it.
+	fn read<T>(
+		&self,
+		format: ClipboardFormat,
+		decode: impl FnOnce(HANDLE) -> Result<T>,
+	) -> Result<Option<T>> {
+		self.reading(|| {
+			if !Self::is_clipboard_format_available(format) {
+				return Ok(None);
+			}
+			let memory = Self::get_clipboard_data::<HGlobal>(format)?;
+			Self::check_read_size(memory, self.max_read_size())?;
+			decode(memory.into()).map(Some)
+		})
+	}
+
+	/// Runs a read of the clipboard, unless there's a read timeout, and the clipboard's owner doesn't respond within it.
+	///
+	/// Reads can only hang when the clipboard's owner has to render the data, and is on another thread,
+	/// so anything else is read straight away.
+	fn reading<T>(&self, read: impl FnOnce() -> Result<T>) -> Result<T> {
+		if let Some(timeout) = self.read_timeout() {
+			if !Self::owned_by_this_thread() {
+				Self::wait_for_owner(timeout)?;
+			}
 		}
-		Ok(handle)
+		read()
 	}
-}
 
-impl Drop for ClipboardHandleInner {
-	fn drop(&mut self) {
-		unsafe {
-			CloseClipboard();
-			CloseWindow(self.window);
+	/// Returns whether the clipboard either has no owner, or is owned by a window on this thread.
+	fn owned_by_this_thread() -> bool {
+		let owner = unsafe { GetClipboardOwner() };
+		owner.is_invalid()
+			|| unsafe { GetWindowThreadProcessId(owner, std::ptr::null_mut()) }
+				== unsafe { GetCurrentThreadId() }
+	}
+
+	/// Waits for up to `timeout` for the clipboard's owner to respond to a message, which it has to be able to do
+	/// to render anything, failing with [`Error::Timeout`] if it doesn't.
+	///
+	/// The clipboard stays open with our window the whole time, so nothing has to be undone if it times out.
+	fn wait_for_owner(timeout: Duration) -> Result<()> {
+		let owner = unsafe { GetClipboardOwner() };
+		let millis = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+		let mut result = 0;
+		let responded = unsafe {
+			SendMessageTimeoutW(
+				owner,
+				WM_NULL,
+				WPARAM(0),
+				LPARAM(0),
+				SMTO_ABORTIFHUNG,
+				millis,
+				&mut result,
+			)
+		}
+		.0 != 0;
+		// If the owner's window is gone, there's nothing left to wait for, and the read can go ahead.
+		if !responded && unsafe { IsWindow(owner) }.as_bool() {
+			trace_event!(warn, ?timeout, "timed out waiting for the clipboard owner");
+			return Err(Error::Timeout);
+		}
+		Ok(())
+	}
+
+	/// Ensures that a global object isn't bigger than the maximum read size, if there is one.
+	fn check_read_size(memory: HGlobal, max_read_size: Option<usize>) -> Result<()> {
+		match max_read_size {
+			Some(limit) => {
+				let size = unsafe { memory.size() };
+				if size > limit {
+					trace_event!(warn, size, limit, "clipboard data is over the read limit");
+					return Err(Error::PayloadTooLarge { size, limit });
+				}
+				Ok(())
+			}
+			None => Ok(()),
+		}
+	}
+
+	fn set_clipboard_data(format: ClipboardFormat, data: impl ClipboardData) -> Result<()> {
+		if unsafe { SetClipboardData(format.into(), data.into_clipboard()) }.is_invalid() {
+			let err = WindowsError::from_last_error();
+			trace_event!(warn, %format, error = err.code(), "failed to set clipboard data");
+			return Err(Error::SetClipboard { format, err });
+		}
+		trace_event!(debug, %format, "set clipboard data");
+		Ok(())
+	}
+
+	/// Puts global memory that we allocated on the clipboard, which takes ownership of it,
+	/// or frees it if the clipboard doesn't.
+	pub(crate) fn set_clipboard_memory<T>(
+		format: ClipboardFormat,
+		memory: LockedPtr<T>,
+	) -> Result<()> {
+		trace_event!(debug, %format, size = memory.size().unwrap_or(0), "setting clipboard memory");
+		let result = Self::set_clipboard_data(format, memory.as_raw_handle());
+		if result.is_err() {
+			memory.free();
+		}
+		result
+	}
+
+	/// Empties the clipboard and puts global memory that we allocated on it,
+	/// freeing the memory if either fails.
+	fn replace_with_memory<T>(&self, format: ClipboardFormat, memory: LockedPtr<T>) -> Result<()> {
+		if let Err(err) = self.empty() {
+			memory.free();
+			return Err(err);
+		}
+		Self::set_clipboard_memory(format, memory)
+	}
+
+	fn get_clipboard_data<H: ClipboardData>(format: ClipboardFormat) -> Result<H> {
+		let handle = unsafe { GetClipboardData(format.into()) };
+		if handle.is_invalid() {
+			let err = WindowsError::from_last_error();
+			trace_event!(warn, %format, error = err.code(), "failed to get clipboard data");
+			return Err(Error::GetClipboard { format, err });
 		}
+		trace_event!(debug, %format, "got clipboard data");
+		Ok(H::from_clipboard(handle))
 	}
 }
@@ -1,79 +1,376 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::error::{Error, Result, WindowsError};
+use crate::{
+	error::{Error, Result, WindowsError},
+	handle::HGlobal,
+};
 use windows::Win32::{
-	Foundation::HANDLE,
-	System::Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+	Foundation::{SetLastError, ERROR_NOT_LOCKED, NO_ERROR},
+	System::Memory::{GLOBAL_ALLOC_FLAGS, GMEM_MOVEABLE, GMEM_ZEROINIT},
 };
 
+/// The most bytes that can be allocated at once, as slices can't be any bigger than this.
+pub(crate) const MAX_ALLOC_SIZE: usize = isize::MAX as usize;
+
+/// Adds up the number of elements needed for some data, without overflowing.
+pub(crate) fn checked_len(len: usize, extra: usize) -> Result<usize> {
+	len.checked_add(extra).ok_or(Error::PayloadTooLarge {
+		size: usize::MAX,
+		limit: MAX_ALLOC_SIZE,
+	})
+}
+
 pub struct LockedPtr<T> {
-	lock: isize,
+	lock: HGlobal,
 	ptr: *mut T,
+	/// Whether `GlobalLock` actually incremented a lock count that we need to decrement.
+	locked: bool,
 }
 
 impl<T> LockedPtr<T> {
-	pub unsafe fn new(handle: HANDLE) -> Result<Self> {
-		let lock = handle.0;
-		let ptr = GlobalLock(lock);
+	pub(crate) unsafe fn new(handle: impl Into<HGlobal>) -> Result<Self> {
+		let lock = handle.into();
+		let ptr = lock.lock();
 		if ptr.is_null() {
 			return Err(Error::Locking(WindowsError::from_last_error()));
 		}
-		let alloc_size = GlobalSize(lock);
+		// For GMEM_FIXED allocations, GlobalLock just hands back the handle itself,
+		// and there's no lock count for us to decrement later.
+		let locked = !lock.is_fixed_at(ptr);
+		let alloc_size = lock.size();
 		if alloc_size == 0 {
-			return Err(Error::InvalidObject(WindowsError::from_last_error()));
+			let err = WindowsError::from_last_error();
+			if locked {
+				unlock(lock);
+			}
+			return Err(Error::InvalidObject(err));
 		}
-		// Sanity check
-		if std::mem::size_of::<T>() > alloc_size {
-			Self::panic_if_invalid_size(alloc_size);
+		// Another program may have put malformed data on the clipboard, so this isn't a bug on our end.
+		let needed = std::mem::size_of::<T>();
+		if needed > alloc_size {
+			if locked {
+				unlock(lock);
+			}
+			return Err(Error::AllocationTooSmall {
+				needed,
+				actual: alloc_size,
+			});
 		}
 		Ok(LockedPtr {
 			lock,
 			ptr: ptr as _,
+			locked,
 		})
 	}
 
+	/// Allocates enough global memory for `amt` elements of `T`.
+	/// The contents of the allocation are left uninitialized.
+	#[cfg(test)]
 	pub fn alloc(amt: usize) -> Result<Self> {
-		let handle = unsafe { GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of::<T>() * amt) };
-		if handle == 0 {
-			return Err(Error::Allocation(WindowsError::from_last_error()));
+		Self::alloc_with_flags(GMEM_MOVEABLE, amt)
+	}
+
+	/// Allocates enough global memory for `amt` elements of `T`, with every byte set to zero.
+	///
+	/// Anything we put on the clipboard can be read by any other program,
+	/// so this should be used whenever not every byte is going to be overwritten.
+	pub fn alloc_zeroed(amt: usize) -> Result<Self> {
+		Self::alloc_with_flags(GMEM_MOVEABLE | GMEM_ZEROINIT, amt)
+	}
+
+	fn alloc_with_flags(flags: GLOBAL_ALLOC_FLAGS, amt: usize) -> Result<Self> {
+		let element_size = std::mem::size_of::<T>();
+		let size = element_size
+			.checked_mul(amt)
+			.ok_or(Error::AllocationOverflow {
+				elements: amt,
+				element_size,
+			})?;
+		if size > MAX_ALLOC_SIZE {
+			return Err(Error::PayloadTooLarge {
+				size,
+				limit: MAX_ALLOC_SIZE,
+			});
+		}
+		// A zero byte allocation comes back already discarded, so it could never be locked.
+		if size == 0 {
+			return Err(Error::EmptyAllocation);
 		}
-		unsafe { Self::new(HANDLE(handle)) }
+		let handle = unsafe { HGlobal::alloc(flags, size) }
+			.ok_or_else(|| Error::Allocation(WindowsError::from_last_error()))?;
+		// Nothing owns the memory until it's returned, so it has to be freed if anything fails before then.
+		let memory = unsafe { Self::new(handle) }.inspect_err(|_| unsafe { handle.free() })?;
+		// GlobalAlloc can round up, but it should never give us less than we asked for.
+		let actual = match memory.size() {
+			Ok(actual) => actual,
+			Err(err) => {
+				memory.free();
+				return Err(err);
+			}
+		};
+		if actual < size {
+			memory.free();
+			return Err(Error::AllocationTooSmall {
+				needed: size,
+				actual,
+			});
+		}
+		Ok(memory)
+	}
+
+	/// Allocates global memory holding exactly the elements of `data`.
+	pub fn alloc_from_slice(data: &[T]) -> Result<Self>
+	where
+		T: Copy,
+	{
+		let mut memory = Self::alloc_zeroed(data.len())?;
+		memory.as_mut_slice()?[..data.len()].copy_from_slice(data);
+		Ok(memory)
+	}
+
+	/// Allocates global memory holding the elements of `data`, followed by a null terminator.
+	pub fn alloc_from_slice_with_nul(data: &[T]) -> Result<Self>
+	where
+		T: Copy + Default,
+	{
+		let mut memory = Self::alloc_zeroed(checked_len(data.len(), 1)?)?;
+		let slice = memory.as_mut_slice()?;
+		slice[..data.len()].copy_from_slice(data);
+		slice[data.len()] = T::default();
+		Ok(memory)
 	}
 
 	/// Returns the size of the allocation, in bytes.
 	pub fn size(&self) -> Result<usize> {
-		let alloc_size = unsafe { GlobalSize(self.lock) };
+		let alloc_size = unsafe { self.lock.size() };
 		if alloc_size == 0 {
 			return Err(Error::InvalidObject(WindowsError::from_last_error()));
 		}
 		Ok(alloc_size)
 	}
 
-	// Seperate function so we can have the #[cold] attribute to tell LLVM "ay this will probably never run"
-	#[cold]
-	fn panic_if_invalid_size(alloc_size: usize) {
-		panic!(
-			"Attempted to create a LockedPtr<{type_name}>, however the allocation size is {alloc_size} bytes, which cannot fit the {type_size} bytes of {type_name}",
-			type_name = std::any::type_name::<T>(),
-			type_size = std::mem::size_of::<T>()
-		);
+	/// Returns the number of whole `T`s that fit in the allocation.
+	///
+	/// Any trailing bytes that don't make up a whole `T` are ignored.
+	pub fn len(&self) -> Result<usize> {
+		let alloc_size = self.size()?;
+		Ok(match std::mem::size_of::<T>() {
+			0 => 0,
+			type_size => alloc_size / type_size,
+		})
+	}
+
+	/// Returns the allocation as a slice of `T`.
+	///
+	/// The allocation is re-validated every time, as the owner of a clipboard object can free it out from under us.
+	pub fn as_slice(&self) -> Result<&[T]> {
+		// GlobalLock never gives us a null pointer, so this is fine even when the length is 0.
+		Ok(unsafe { std::slice::from_raw_parts(self.ptr, self.len()?) })
+	}
+
+	/// Returns the allocation as a mutable slice of `T`.
+	pub fn as_mut_slice(&mut self) -> Result<&mut [T]> {
+		Ok(unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len()?) })
+	}
+
+	/// Returns the raw bytes of the allocation.
+	pub fn as_bytes(&self) -> Result<&[u8]> {
+		Ok(unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.size()?) })
+	}
+
+	/// Returns the raw bytes of the allocation, mutably.
+	pub fn as_bytes_mut(&mut self) -> Result<&mut [u8]> {
+		Ok(unsafe { std::slice::from_raw_parts_mut(self.ptr as *mut u8, self.size()?) })
 	}
 
+	#[cfg(any(test, feature = "image"))]
 	pub fn as_ptr(&self) -> *const T {
 		self.ptr as _
 	}
 
+	#[cfg(feature = "image")]
 	pub fn as_mut_ptr(&self) -> *mut T {
 		self.ptr
 	}
 
-	pub fn as_raw_handle(&self) -> HANDLE {
-		HANDLE(self.lock)
+	pub(crate) fn as_raw_handle(&self) -> HGlobal {
+		self.lock
+	}
+
+	/// Unlocks and frees memory that we allocated, for when the clipboard didn't take ownership of it.
+	pub fn free(self) {
+		let handle = self.lock;
+		drop(self);
+		unsafe { handle.free() };
 	}
 }
 
 impl<T> Drop for LockedPtr<T> {
 	fn drop(&mut self) {
-		unsafe { GlobalUnlock(self.lock) };
+		if self.locked {
+			unsafe { unlock(self.lock) };
+		}
+	}
+}
+
+/// Decrements the lock count of a global object.
+unsafe fn unlock(lock: HGlobal) {
+	// GlobalUnlock returns FALSE both on failure and when the lock count hits zero,
+	// so the last error is the only way to tell the two apart.
+	SetLastError(NO_ERROR);
+	if !lock.unlock().as_bool() {
+		let err = WindowsError::from_last_error();
+		debug_assert!(
+			err.code() == NO_ERROR.0 || err.code() == ERROR_NOT_LOCKED.0,
+			"Failed to unlock global object: {}",
+			err
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use windows::Win32::{Graphics::Gdi::BITMAPINFO, System::Memory::GMEM_FIXED};
+
+	/// Allocates global memory that's freed once the returned guard is dropped.
+	fn global_alloc(
+		flags: GLOBAL_ALLOC_FLAGS,
+		size: usize,
+	) -> impl std::ops::Deref<Target = HGlobal> {
+		let handle =
+			unsafe { HGlobal::alloc(flags, size) }.expect("failed to allocate global memory");
+		scopeguard::guard(handle, |handle| unsafe { handle.free() })
+	}
+
+	#[test]
+	fn undersized_allocation() {
+		let memory = global_alloc(GMEM_MOVEABLE, 4);
+		let handle = *memory;
+		match unsafe { LockedPtr::<BITMAPINFO>::new(handle) } {
+			Err(Error::AllocationTooSmall { needed, actual }) => {
+				assert_eq!(needed, std::mem::size_of::<BITMAPINFO>());
+				assert!(actual < needed);
+			}
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("undersized allocation was accepted"),
+		}
+	}
+
+	#[test]
+	fn slice_truncates_remainder() {
+		let mut locked = LockedPtr::<u8>::alloc(7).expect("failed to allocate");
+		locked
+			.as_bytes_mut()
+			.expect("invalid object")
+			.copy_from_slice(&[1, 0, 2, 0, 3, 0, 4]);
+		let handle = locked.as_raw_handle();
+		drop(locked);
+		let wide = unsafe { LockedPtr::<u16>::new(handle) }.expect("failed to lock");
+		assert_eq!(wide.len().expect("invalid object"), 3);
+		assert_eq!(wide.as_slice().expect("invalid object"), &[1, 2, 3]);
+		assert_eq!(wide.as_bytes().expect("invalid object").len(), 7);
+		drop(wide);
+		unsafe { handle.free() };
+	}
+
+	#[test]
+	fn overflowing_allocation() {
+		match LockedPtr::<u16>::alloc(usize::MAX / 2 + 1) {
+			Err(err) => assert_eq!(
+				err,
+				Error::AllocationOverflow {
+					elements: usize::MAX / 2 + 1,
+					element_size: 2
+				}
+			),
+			Ok(_) => panic!("overflowing allocation was accepted"),
+		}
+		match LockedPtr::<u8>::alloc(MAX_ALLOC_SIZE + 1) {
+			Err(Error::PayloadTooLarge { size, limit }) => {
+				assert_eq!(size, MAX_ALLOC_SIZE + 1);
+				assert_eq!(limit, MAX_ALLOC_SIZE);
+			}
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("oversized allocation was accepted"),
+		}
+		assert!(matches!(
+			checked_len(usize::MAX, 1),
+			Err(Error::PayloadTooLarge { .. })
+		));
+	}
+
+	#[test]
+	fn zero_element_allocation() {
+		assert!(matches!(
+			LockedPtr::<u16>::alloc(0),
+			Err(Error::EmptyAllocation)
+		));
+		assert!(matches!(
+			LockedPtr::<u8>::alloc_from_slice(&[]),
+			Err(Error::EmptyAllocation)
+		));
+		assert!(matches!(
+			LockedPtr::<()>::alloc_zeroed(16),
+			Err(Error::EmptyAllocation)
+		));
+	}
+
+	#[test]
+	fn alloc_from_slice_with_nul() {
+		let data = [0x68_u16, 0x69, 0x21];
+		let locked =
+			LockedPtr::<u16>::alloc_from_slice_with_nul(&data).expect("failed to allocate");
+		let slice = locked.as_slice().expect("invalid object");
+		assert!(slice.len() > data.len());
+		assert_eq!(&slice[..data.len()], &data);
+		assert_eq!(slice[data.len()], 0);
+	}
+
+	#[test]
+	fn alloc_zeroed_tail() {
+		let mut locked = LockedPtr::<u8>::alloc_zeroed(64).expect("failed to allocate");
+		locked.as_bytes_mut().expect("invalid object")[..3].copy_from_slice(b"abc");
+		let bytes = locked.as_bytes().expect("invalid object");
+		assert_eq!(&bytes[..3], b"abc");
+		assert!(bytes[3..].iter().all(|byte| *byte == 0));
+
+		let locked = LockedPtr::<u8>::alloc_from_slice(b"abc").expect("failed to allocate");
+		let bytes = locked.as_bytes().expect("invalid object");
+		assert_eq!(&bytes[..3], b"abc");
+		assert!(bytes[3..].iter().all(|byte| *byte == 0));
+	}
+
+	#[test]
+	fn zero_sized_type() {
+		let memory = global_alloc(GMEM_MOVEABLE, 4);
+		let handle = *memory;
+		let locked = unsafe { LockedPtr::<()>::new(handle) }.expect("failed to lock");
+		assert_eq!(locked.len().expect("invalid object"), 0);
+		assert!(locked.as_slice().expect("invalid object").is_empty());
+		assert_eq!(locked.as_bytes().expect("invalid object").len(), 4);
+	}
+
+	#[test]
+	fn moveable_lock_count() {
+		let memory = global_alloc(GMEM_MOVEABLE, 16);
+		let handle = *memory;
+		let locked = unsafe { LockedPtr::<u8>::new(handle) }.expect("failed to lock");
+		assert!(locked.locked);
+		assert_eq!(unsafe { handle.lock_count() }, 1);
+		drop(locked);
+		assert_eq!(unsafe { handle.lock_count() }, 0);
+	}
+
+	#[test]
+	fn fixed_allocation() {
+		let memory = global_alloc(GMEM_FIXED, 16);
+		let handle = *memory;
+		let locked = unsafe { LockedPtr::<u8>::new(handle) }.expect("failed to lock");
+		assert!(!locked.locked);
+		assert!(handle.is_fixed_at(locked.as_ptr() as _));
+		assert_eq!(locked.as_slice().expect("invalid object").len(), 16);
+		// Dropping this must not trip the unlock assertion.
+		drop(locked);
 	}
 }
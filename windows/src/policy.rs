@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/// Whether the clipboard's contents may be saved in the Windows 10+ clipboard history.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HistoryPolicy {
+	Allow,
+	Deny,
+}
+
+/// Whether the clipboard's contents may be synced to the user's other devices through the cloud clipboard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CloudPolicy {
+	Allow,
+	Deny,
+}
+
+impl HistoryPolicy {
+	pub(crate) fn to_dword(self) -> u32 {
+		match self {
+			Self::Allow => 1,
+			Self::Deny => 0,
+		}
+	}
+}
+
+impl CloudPolicy {
+	pub(crate) fn to_dword(self) -> u32 {
+		match self {
+			Self::Allow => 1,
+			Self::Deny => 0,
+		}
+	}
+}
+
+/// Reads a DWORD policy marker, treating any non-zero value as allowed.
+pub(crate) fn dword_allowed(data: &[u8]) -> bool {
+	let mut dword = [0_u8; 4];
+	let len = data.len().min(dword.len());
+	dword[..len].copy_from_slice(&data[..len]);
+	u32::from_le_bytes(dword) != 0
+}
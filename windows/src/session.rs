@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+	error::{Error, Result},
+	format::{self, files::SetFilesOptions, string::NulPolicy, ClipboardFormat},
+	ClipboardHandleInner,
+};
+use std::{
+	path::{Path, PathBuf},
+	sync::atomic::Ordering,
+};
+
+/// A group of clipboard operations that happen together, with nothing else changing the clipboard in between.
+///
+/// Reads happen straight away, while writes are held back until [`ClipboardSession::commit`],
+/// so the clipboard is left untouched if the session is aborted or dropped instead.
+/// Only one session can be active at a time.
+pub struct ClipboardSession<'handle> {
+	handle: &'handle ClipboardHandleInner,
+	pending: Vec<(ClipboardFormat, Vec<u8>)>,
+}
+
+impl<'handle> ClipboardSession<'handle> {
+	pub(crate) fn new(handle: &'handle ClipboardHandleInner) -> Result<Self> {
+		if handle.clipboard.in_session.swap(true, Ordering::SeqCst) {
+			return Err(Error::ClipboardAlreadyOpen);
+		}
+		Ok(Self {
+			handle,
+			pending: Vec::new(),
+		})
+	}
+
+	pub fn string(&self) -> Result<Option<String>> {
+		self.handle.string()
+	}
+
+	pub fn string_unicode(&self) -> Result<Option<String>> {
+		self.handle.string_unicode()
+	}
+
+	pub fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		self.handle.files()
+	}
+
+	#[cfg(feature = "image")]
+	pub fn image(&self) -> Result<Option<image::RgbImage>> {
+		self.handle.image()
+	}
+
+	pub fn get_raw(&self, format: ClipboardFormat) -> Result<Option<Vec<u8>>> {
+		self.handle.get_raw(format)
+	}
+
+	pub fn available_formats(&self) -> Result<Vec<ClipboardFormat>> {
+		self.handle.available_formats()
+	}
+
+	/// Puts a string on the clipboard when the session is committed.
+	///
+	/// Like [`ClipboardHandleInner::set_string`], this fails with [`Error::InteriorNul`]
+	/// if the string has a NUL character in it, see [`Self::set_string_with_nul_policy`]
+	/// to do something else instead.
+	pub fn set_string<StringType: ToString>(&mut self, string: StringType) -> Result<()> {
+		self.set_string_with_nul_policy(string, NulPolicy::default())
+	}
+
+	/// Like [`Self::set_string`], but with a choice of what to do if the string has a NUL character in it.
+	pub fn set_string_with_nul_policy<StringType: ToString>(
+		&mut self,
+		string: StringType,
+		policy: NulPolicy,
+	) -> Result<()> {
+		let string = string.to_string();
+		let string = policy
+			.apply(&string)?
+			.encode_utf16()
+			.chain(std::iter::once(0))
+			.flat_map(u16::to_le_bytes)
+			.collect();
+		self.pending.push((ClipboardFormat::UnicodeText, string));
+		Ok(())
+	}
+
+	/// Puts a list of files on the clipboard when the session is committed.
+	pub fn set_files<I>(&mut self, paths: I) -> Result<()>
+	where
+		I: IntoIterator,
+		I::Item: AsRef<Path>,
+	{
+		self.set_files_with_options(paths, &SetFilesOptions::default())
+	}
+
+	/// Like [`Self::set_files`], but with control over the `DROPFILES` header that the list starts with.
+	pub fn set_files_with_options<I>(&mut self, paths: I, options: &SetFilesOptions) -> Result<()>
+	where
+		I: IntoIterator,
+		I::Item: AsRef<Path>,
+	{
+		let files = format::files::encode(paths, options)?;
+		self.pending.push((ClipboardFormat::DropHandle, files));
+		Ok(())
+	}
+
+	/// Puts raw bytes on the clipboard when the session is committed.
+	pub fn set_raw(&mut self, format: ClipboardFormat, data: &[u8]) -> Result<()> {
+		self.pending.push((format, data.to_vec()));
+		Ok(())
+	}
+
+	/// Replaces the clipboard's contents with everything that was set during the session.
+	///
+	/// If nothing was set, the clipboard is left as it is.
+	pub fn commit(mut self) -> Result<()> {
+		let pending = std::mem::take(&mut self.pending);
+		if pending.is_empty() {
+			return Ok(());
+		}
+		self.handle.empty()?;
+		for (format, data) in pending {
+			self.handle.set_raw(format, &data)?;
+		}
+		Ok(())
+	}
+
+	/// Ends the session without writing anything to the clipboard, which is the same as dropping it.
+	pub fn abort(self) {}
+}
+
+impl Drop for ClipboardSession<'_> {
+	fn drop(&mut self) {
+		self.handle
+			.clipboard
+			.in_session
+			.store(false, Ordering::SeqCst);
+	}
+}
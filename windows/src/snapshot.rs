@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{error::Result, format::ClipboardFormat, ClipboardHandleInner};
+
+/// A copy of the clipboard's contents, which can be put back on the clipboard later.
+///
+/// Only formats stored in global memory can be snapshotted; handle-based formats such as
+/// [`ClipboardFormat::Bitmap`] are skipped, but are usually synthesized again from the
+/// formats that were restored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ClipboardSnapshot {
+	formats: Vec<(ClipboardFormat, Vec<u8>)>,
+}
+
+impl ClipboardSnapshot {
+	/// Copies every format on the clipboard, or returns `None` if they add up to more than `max_size` bytes.
+	///
+	/// Fails if any of the formats can't be read, rather than leaving it out of the snapshot.
+	pub(crate) fn capture(
+		handle: &ClipboardHandleInner,
+		max_size: Option<usize>,
+	) -> Result<Option<Self>> {
+		let mut formats = Vec::new();
+		let mut size = 0_usize;
+		for format in handle.available_formats()? {
+			if matches!(
+				format,
+				ClipboardFormat::Bitmap | ClipboardFormat::EnhMetafile
+			) {
+				continue;
+			}
+			// Check the size before copying anything, so huge formats are never read.
+			if let Some(max_size) = max_size {
+				size = size.saturating_add(handle.format_size(format)?.unwrap_or(0));
+				if size > max_size {
+					return Ok(None);
+				}
+			}
+			if let Some(data) = handle.get_raw(format)? {
+				formats.push((format, data));
+			}
+		}
+		Ok(Some(Self { formats }))
+	}
+
+	/// Replaces the clipboard's contents with the snapshotted ones.
+	pub(crate) fn restore(&self, handle: &ClipboardHandleInner) -> Result<()> {
+		handle.empty()?;
+		for (format, data) in &self.formats {
+			handle.set_raw(*format, data)?;
+		}
+		Ok(())
+	}
+
+	/// Returns the snapshotted formats, along with their data.
+	pub fn formats(&self) -> &[(ClipboardFormat, Vec<u8>)] {
+		&self.formats
+	}
+
+	/// Returns the snapshotted data for the given format, if it was on the clipboard.
+	pub fn get(&self, format: ClipboardFormat) -> Option<&[u8]> {
+		self.formats
+			.iter()
+			.find(|(snapshotted, _)| *snapshotted == format)
+			.map(|(_, data)| data.as_slice())
+	}
+
+	/// Returns whether the given format was on the clipboard.
+	pub fn contains(&self, format: ClipboardFormat) -> bool {
+		self.get(format).is_some()
+	}
+
+	/// Returns the total number of bytes in the snapshot.
+	pub fn size(&self) -> usize {
+		self.formats.iter().map(|(_, data)| data.len()).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.formats.is_empty()
+	}
+}
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The hidden window that the clipboard is opened with, and the clipboard messages it's sent.
+//!
+//! Windows sends these messages to the window that owns the clipboard contents,
+//! which is only the case after we've emptied the clipboard ourselves.
+
+use crate::{
+	error::{Error, Result, WindowsError},
+	format::ClipboardFormat,
+	lock::LockedPtr,
+	ClipboardHandleInner,
+};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+use windows::{
+	core::PCWSTR,
+	Win32::{
+		Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM},
+		System::DataExchange::{CloseClipboard, GetClipboardOwner, OpenClipboard},
+		UI::WindowsAndMessaging::{
+			CreateWindowExW, DefWindowProcW, RegisterClassExW, HMENU, HWND_MESSAGE,
+			WINDOW_EX_STYLE, WINDOW_STYLE, WM_DESTROYCLIPBOARD, WM_RENDERALLFORMATS,
+			WM_RENDERFORMAT, WNDCLASSEXW,
+		},
+	},
+};
+
+/// Produces the data for a format that was promised to the clipboard, but not rendered yet.
+pub(crate) type Renderer = Box<dyn FnMut() -> Vec<u8> + Send>;
+
+/// Called when something else takes over the clipboard.
+pub(crate) type OwnershipLostCallback = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct WindowState {
+	promised: HashMap<ClipboardFormat, Renderer>,
+	on_ownership_lost: Option<OwnershipLostCallback>,
+	/// Whether we're emptying the clipboard ourselves, in which case we haven't lost it to anyone.
+	emptying: bool,
+}
+
+/// There's only ever one clipboard window at a time, as there's only ever one clipboard handle.
+static STATE: Lazy<Mutex<WindowState>> = Lazy::new(Mutex::default);
+
+/// Registers our window class if needed, and creates a message-only window with it.
+pub(crate) fn create() -> Result<HWND> {
+	static CLASS_NAME: &[u16] = &[0x63, 0x6C, 0x69, 0x70, 0x65, 0x65, 0x00]; // "clipee" + \0
+	let class = WNDCLASSEXW {
+		cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+		lpfnWndProc: Some(window_proc),
+		lpszClassName: PCWSTR(CLASS_NAME.as_ptr()),
+		..Default::default()
+	};
+	// This fails if the class is already registered, which is fine, as it'd be registered with the same procedure.
+	unsafe { RegisterClassExW(&class) };
+	let window = unsafe {
+		CreateWindowExW(
+			WINDOW_EX_STYLE::default(),
+			PCWSTR(CLASS_NAME.as_ptr()),
+			PCWSTR::default(),
+			WINDOW_STYLE::default(),
+			0,
+			0,
+			0,
+			0,
+			HWND_MESSAGE,
+			HMENU::default(),
+			HINSTANCE::default(),
+			std::ptr::null(),
+		)
+	};
+	if window.is_invalid() {
+		return Err(Error::CreateWindow(WindowsError::from_last_error()));
+	}
+	Ok(window)
+}
+
+/// Remembers how to render a format that's been promised to the clipboard.
+pub(crate) fn promise(format: ClipboardFormat, renderer: Renderer) {
+	STATE.lock().promised.insert(format, renderer);
+}
+
+pub(crate) fn set_on_ownership_lost(callback: Option<OwnershipLostCallback>) {
+	STATE.lock().on_ownership_lost = callback;
+}
+
+/// Empties the clipboard with `empty`, without treating that as losing ownership of it.
+pub(crate) fn empty_quietly<T>(empty: impl FnOnce() -> T) -> T {
+	STATE.lock().emptying = true;
+	let result = empty();
+	STATE.lock().emptying = false;
+	result
+}
+
+/// Forgets everything that was set up for the window, once it's gone.
+pub(crate) fn reset() {
+	*STATE.lock() = WindowState::default();
+}
+
+/// Renders a promised format onto the clipboard, which must already be open.
+fn render(format: ClipboardFormat) {
+	// The renderer is taken out, so that it can't deadlock by using the clipboard itself.
+	let renderer = STATE.lock().promised.remove(&format);
+	if let Some(mut renderer) = renderer {
+		let data = renderer();
+		// There's no one to report failure to; whoever asked for the data will just not get it.
+		if let Ok(memory) = LockedPtr::<u8>::alloc_from_slice(&data) {
+			let _ = ClipboardHandleInner::set_clipboard_memory(format, memory);
+		}
+	}
+}
+
+unsafe extern "system" fn window_proc(
+	window: HWND,
+	message: u32,
+	wparam: WPARAM,
+	lparam: LPARAM,
+) -> LRESULT {
+	match message {
+		// Someone asked for a promised format; they have the clipboard open already.
+		WM_RENDERFORMAT => {
+			if let Some(format) = ClipboardFormat::try_from_u32(wparam.0 as u32) {
+				trace_event!(debug, %format, "rendering promised format");
+				render(format);
+			}
+			LRESULT(0)
+		}
+		// The window is being destroyed while we own the clipboard, so every promise has to be kept now,
+		// after making sure that no one else took the clipboard in the meantime.
+		WM_RENDERALLFORMATS => {
+			trace_event!(debug, "rendering all promised formats");
+			if OpenClipboard(window).as_bool() {
+				if GetClipboardOwner() == window {
+					let formats = STATE.lock().promised.keys().copied().collect::<Vec<_>>();
+					for format in formats {
+						render(format);
+					}
+				}
+				CloseClipboard();
+			}
+			LRESULT(0)
+		}
+		// Whatever we promised is gone from the clipboard either way, but only someone else emptying it
+		// means we've lost it.
+		WM_DESTROYCLIPBOARD => {
+			let callback = {
+				let mut state = STATE.lock();
+				state.promised.clear();
+				if state.emptying {
+					None
+				} else {
+					trace_event!(debug, "lost clipboard ownership");
+					state.on_ownership_lost.clone()
+				}
+			};
+			if let Some(callback) = callback {
+				callback();
+			}
+			LRESULT(0)
+		}
+		_ => DefWindowProcW(window, message, wparam, lparam),
+	}
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Each test uses its own subset of these.
+#![allow(dead_code)]
+
+use clipee_windows::exclusive::ExclusiveClipboard;
+use windows::{
+	core::PCWSTR,
+	Win32::{
+		Foundation::{CloseHandle, BOOL, HANDLE, HINSTANCE, HWND},
+		System::Threading::{CreateMutexW, ReleaseMutex, WaitForSingleObject},
+		UI::WindowsAndMessaging::{
+			CreateWindowExW, HMENU, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE,
+		},
+	},
+};
+
+pub fn wide(string: &str) -> Vec<u16> {
+	string.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Creates a message-only window on this thread, for opening the clipboard as someone other than clipee.
+pub fn message_window() -> HWND {
+	let class = wide("STATIC");
+	let window = unsafe {
+		CreateWindowExW(
+			WINDOW_EX_STYLE::default(),
+			PCWSTR(class.as_ptr()),
+			PCWSTR::default(),
+			WINDOW_STYLE::default(),
+			0,
+			0,
+			0,
+			0,
+			HWND_MESSAGE,
+			HMENU::default(),
+			HINSTANCE::default(),
+			std::ptr::null(),
+		)
+	};
+	assert!(!window.is_invalid(), "failed to create window");
+	window
+}
+
+/// Holds the mutex that [`ExclusiveClipboard`] uses, without opening the clipboard,
+/// for tests that need it to be opened by something else.
+pub struct ClipboardLock(HANDLE);
+
+impl ClipboardLock {
+	pub fn acquire() -> Self {
+		let name = wide(ExclusiveClipboard::MUTEX_NAME);
+		let mutex =
+			unsafe { CreateMutexW(std::ptr::null(), BOOL::from(false), PCWSTR(name.as_ptr())) };
+		assert!(!mutex.is_invalid(), "failed to create mutex");
+		unsafe { WaitForSingleObject(mutex, u32::MAX) };
+		Self(mutex)
+	}
+}
+
+impl Drop for ClipboardLock {
+	fn drop(&mut self) {
+		unsafe {
+			ReleaseMutex(self.0);
+			CloseHandle(self.0);
+		}
+	}
+}
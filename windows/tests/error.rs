@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{
+	error::{Error, WindowsError},
+	format::ClipboardFormat,
+};
+
+#[test]
+pub fn windows_error_codes() {
+	let access_denied = WindowsError::from_code(5);
+	assert_eq!(access_denied.code(), 5);
+	assert_eq!(u32::from(access_denied), 5);
+	assert!(access_denied.is_access_denied());
+	assert!(!access_denied.is_out_of_memory());
+
+	let out_of_memory = WindowsError::from_code(8);
+	assert!(out_of_memory.is_out_of_memory());
+	assert!(!out_of_memory.is_access_denied());
+
+	let err = Error::OpenClipboard(access_denied);
+	assert_eq!(err.windows_error(), Some(&access_denied));
+	assert_eq!(Error::NoFiles.windows_error(), None);
+}
+
+#[test]
+pub fn unknown_error_code_display() {
+	// Bit 29 marks application-defined error codes, so the system has no message for this one.
+	let err = WindowsError::from_code(0x2000_1234);
+	assert_eq!(err.to_string(), "Windows error 0x20001234");
+	let err = Error::GetClipboard {
+		format: ClipboardFormat::UnicodeText,
+		err,
+	};
+	assert_eq!(
+		err.to_string(),
+		"Failed to get CF_UNICODETEXT clipboard data: Windows error 0x20001234"
+	);
+}
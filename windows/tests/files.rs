@@ -1,12 +1,19 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use clipee_windows::ClipboardHandle;
+use clipee_windows::{
+	error::Error,
+	exclusive::ExclusiveClipboard,
+	format::{files::SetFilesOptions, ClipboardFormat},
+};
+use std::{
+	ffi::OsString,
+	os::windows::ffi::{OsStrExt, OsStringExt},
+	path::PathBuf,
+};
 
 #[test]
 pub fn round_trip_files() {
-	use std::path::PathBuf;
-
-	let handle = ClipboardHandle::new().expect("failed to open clipboard");
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
 	let files_list = vec![
 		PathBuf::from("C:\\Users\\"),
 		PathBuf::from("C:\\Users\\Clipboard\\"),
@@ -30,3 +37,166 @@ pub fn round_trip_files() {
 		.expect("failed to get files from clipboard");
 	assert_eq!(files_list, files, "File list didn't survive the round-trip");
 }
+
+#[test]
+pub fn set_files_from_iterator() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_files((1..=3).map(|idx| format!("C:\\test{}.txt", idx)))
+		.expect("failed to set files to clipboard");
+	assert_eq!(
+		handle.files().expect("failed to get files from clipboard"),
+		Some(vec![
+			PathBuf::from("C:\\test1.txt"),
+			PathBuf::from("C:\\test2.txt"),
+			PathBuf::from("C:\\test3.txt"),
+		])
+	);
+	assert_eq!(
+		handle.set_files(std::iter::empty::<PathBuf>()),
+		Err(Error::NoFiles)
+	);
+}
+
+#[test]
+pub fn count_and_nth_file() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	assert_eq!(handle.file_count().expect("failed to count files"), None);
+	assert_eq!(handle.nth_file(0).expect("failed to get file"), None);
+
+	let files_list = (0..10)
+		.map(|idx| PathBuf::from(format!("C:\\Users\\Clipboard\\{}.txt", idx)))
+		.collect::<Vec<_>>();
+	handle
+		.set_files(&files_list)
+		.expect("failed to set files to clipboard");
+	let files = handle
+		.files()
+		.expect("failed to get files from clipboard")
+		.expect("files weren't set in clipboard");
+	assert_eq!(
+		handle.file_count().expect("failed to count files"),
+		Some(files.len())
+	);
+	for (idx, file) in files.iter().enumerate() {
+		assert_eq!(
+			handle.nth_file(idx).expect("failed to get file").as_ref(),
+			Some(file)
+		);
+	}
+	assert_eq!(
+		handle.nth_file(files.len()).expect("failed to get file"),
+		None
+	);
+}
+
+#[test]
+pub fn empty_file_list() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	// A DROPFILES header with wide paths, followed by nothing but the list's double null terminator.
+	let mut drop_files = Vec::new();
+	drop_files.extend_from_slice(&20_u32.to_le_bytes());
+	drop_files.extend_from_slice(&[0; 12]);
+	drop_files.extend_from_slice(&1_u32.to_le_bytes());
+	drop_files.extend_from_slice(&[0; 4]);
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(ClipboardFormat::DropHandle, &drop_files)
+		.expect("failed to set raw data to clipboard");
+	assert_eq!(
+		handle.files().expect("failed to get files from clipboard"),
+		Some(Vec::new())
+	);
+	assert_eq!(handle.file_count().expect("failed to count files"), Some(0));
+	assert_eq!(handle.nth_file(0).expect("failed to get file"), None);
+}
+
+#[test]
+pub fn unpaired_surrogate_path() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	// C:\a<lone high surrogate>.txt, which NTFS allows, but isn't valid Unicode.
+	let path = [0x43, 0x3A, 0x5C, 0x61, 0xD800, 0x2E, 0x74, 0x78, 0x74];
+	let mut drop_files = Vec::new();
+	drop_files.extend_from_slice(&20_u32.to_le_bytes());
+	drop_files.extend_from_slice(&[0; 12]);
+	drop_files.extend_from_slice(&1_u32.to_le_bytes());
+	drop_files.extend(
+		path.iter()
+			.chain(&[0, 0])
+			.flat_map(|unit: &u16| unit.to_le_bytes()),
+	);
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(ClipboardFormat::DropHandle, &drop_files)
+		.expect("failed to set raw data to clipboard");
+	let files = handle
+		.files()
+		.expect("failed to get files from clipboard")
+		.expect("files weren't set in clipboard");
+	assert_eq!(files.len(), 1);
+	assert_eq!(
+		files[0].as_os_str().encode_wide().collect::<Vec<_>>(),
+		path,
+		"path wasn't returned exactly as it was"
+	);
+}
+
+#[test]
+pub fn set_unpaired_surrogate_path() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let path = PathBuf::from(OsString::from_wide(&[
+		0x43, 0x3A, 0x5C, 0x61, 0xD800, 0x2E, 0x74, 0x78, 0x74,
+	]));
+	// The empty path is skipped, rather than ending the list before the next one.
+	let files = [path.clone(), PathBuf::new(), PathBuf::from("C:\\test.txt")];
+	handle
+		.set_files(&files)
+		.expect("failed to set files to clipboard");
+	assert_eq!(
+		handle.files().expect("failed to get files from clipboard"),
+		Some(vec![path, PathBuf::from("C:\\test.txt")])
+	);
+	assert_eq!(
+		handle.set_files([PathBuf::new()]),
+		Err(Error::NoFiles),
+		"a list of only empty paths should be rejected"
+	);
+}
+
+#[test]
+pub fn set_files_with_options() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let files = [PathBuf::from("C:\\test.txt")];
+	let header = |handle: &ExclusiveClipboard| {
+		let drop_files = handle
+			.get_raw(ClipboardFormat::DropHandle)
+			.expect("failed to get raw data from clipboard")
+			.expect("files weren't set in clipboard");
+		drop_files[4..16].to_vec()
+	};
+	handle
+		.set_files(&files)
+		.expect("failed to set files to clipboard");
+	// The point is (0, 0) in screen coordinates by default.
+	assert_eq!(header(&handle), [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+	handle
+		.set_files_with_options(
+			&files,
+			&SetFilesOptions::default().point(10, -20).non_client(false),
+		)
+		.expect("failed to set files to clipboard");
+	assert_eq!(
+		header(&handle),
+		[
+			10_i32.to_le_bytes(),
+			(-20_i32).to_le_bytes(),
+			0_i32.to_le_bytes()
+		]
+		.concat()
+	);
+	assert_eq!(
+		handle.files().expect("failed to get files from clipboard"),
+		Some(files.to_vec())
+	);
+}
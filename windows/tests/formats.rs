@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{exclusive::ExclusiveClipboard, format::ClipboardFormat};
+
+#[test]
+pub fn has_format() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_string("formats test")
+		.expect("failed to set string to clipboard");
+	assert!(handle
+		.available_formats()
+		.expect("failed to get available formats")
+		.contains(&ClipboardFormat::UnicodeText));
+	assert!(handle
+		.has_format(ClipboardFormat::UnicodeText)
+		.expect("failed to check format"));
+	assert!(!handle
+		.has_format(ClipboardFormat::Wave)
+		.expect("failed to check format"));
+	assert!(handle
+		.has_any_format(&[ClipboardFormat::Wave, ClipboardFormat::UnicodeText])
+		.expect("failed to check format"));
+	assert!(!handle.has_any_format(&[]).expect("failed to check format"));
+}
+
+#[test]
+pub fn formats_iter() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_string("formats test")
+		.expect("failed to set string to clipboard");
+	let registered =
+		ClipboardFormat::register("clipee formats test").expect("failed to register format");
+	handle
+		.set_raw(registered, b"raw")
+		.expect("failed to set raw data to clipboard");
+
+	let first = handle
+		.formats_iter()
+		.next()
+		.expect("clipboard has no formats")
+		.expect("failed to enumerate formats");
+	assert_eq!(first.format, Some(ClipboardFormat::UnicodeText));
+
+	// Stopping early shouldn't affect the next enumeration.
+	assert_eq!(handle.formats_iter().take(1).count(), 1);
+	let formats = handle
+		.formats_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.expect("failed to enumerate formats");
+	assert!(formats
+		.iter()
+		.any(|info| info.format == Some(ClipboardFormat::UnicodeText)));
+	assert!(formats
+		.iter()
+		.any(|info| info.id == u32::from(registered) && info.format == Some(registered)));
+}
+
+#[test]
+pub fn display_and_parse() {
+	let registered =
+		ClipboardFormat::register("clipee display test").expect("failed to register format");
+	for format in [
+		ClipboardFormat::Text,
+		ClipboardFormat::Bitmap,
+		ClipboardFormat::BitmapInfo,
+		ClipboardFormat::BitmapV5,
+		ClipboardFormat::DropHandle,
+		ClipboardFormat::UnicodeText,
+		ClipboardFormat::EnhMetafile,
+		ClipboardFormat::Wave,
+		registered,
+	] {
+		let name = format.to_string();
+		assert_eq!(
+			name.parse::<ClipboardFormat>()
+				.expect("failed to parse format"),
+			format,
+			"{} didn't round-trip",
+			name
+		);
+	}
+	assert_eq!(ClipboardFormat::UnicodeText.to_string(), "CF_UNICODETEXT");
+	assert_eq!(ClipboardFormat::DropHandle.to_string(), "CF_HDROP");
+	assert_eq!(registered.to_string(), "clipee display test");
+
+	for (alias, format) in [
+		("text", ClipboardFormat::UnicodeText),
+		("Unicode", ClipboardFormat::UnicodeText),
+		("files", ClipboardFormat::DropHandle),
+		("bitmap", ClipboardFormat::Bitmap),
+		("cf_text", ClipboardFormat::Text),
+	] {
+		assert_eq!(
+			alias
+				.parse::<ClipboardFormat>()
+				.expect("failed to parse format"),
+			format
+		);
+	}
+	let html = "html"
+		.parse::<ClipboardFormat>()
+		.expect("failed to parse format");
+	assert_eq!(html.to_string(), "HTML Format");
+	assert_eq!(
+		format!("#{}", u32::from(registered))
+			.parse::<ClipboardFormat>()
+			.expect("failed to parse format"),
+		registered
+	);
+	assert!("#12345".parse::<ClipboardFormat>().is_err());
+}
+
+#[test]
+pub fn format_size() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let format =
+		ClipboardFormat::register("clipee format size test").expect("failed to register format");
+	handle.empty().expect("failed to empty clipboard");
+	assert_eq!(
+		handle
+			.format_size(format)
+			.expect("failed to get format size"),
+		None
+	);
+	handle
+		.set_raw(format, &[7; 1000])
+		.expect("failed to set raw data to clipboard");
+	handle
+		.set_raw(ClipboardFormat::UnicodeText, &[0x61, 0, 0x62, 0, 0, 0])
+		.expect("failed to set raw data to clipboard");
+	let size = handle
+		.format_size(format)
+		.expect("failed to get format size")
+		.expect("format wasn't set in clipboard");
+	let data = handle
+		.get_raw(format)
+		.expect("failed to get raw data from clipboard")
+		.expect("format wasn't set in clipboard");
+	assert_eq!(size, data.len());
+	assert!(size >= 1000);
+	let text_size = handle
+		.format_size(ClipboardFormat::UnicodeText)
+		.expect("failed to get format size")
+		.expect("text wasn't set in clipboard");
+	// Windows synthesizes other text formats too, which count towards the total.
+	assert!(
+		handle.total_size().expect("failed to get total size") >= size + text_size,
+		"total size doesn't include every format"
+	);
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{exclusive::ExclusiveClipboard, format::ClipboardFormat};
+
+#[test]
+pub fn content_hash() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	assert_eq!(
+		handle.content_hash().expect("failed to hash clipboard"),
+		None
+	);
+
+	handle
+		.set_string("hash me")
+		.expect("failed to set string to clipboard");
+	let first = handle
+		.content_hash()
+		.expect("failed to hash clipboard")
+		.expect("clipboard has no contents");
+	assert_eq!(
+		handle.content_hash().expect("failed to hash clipboard"),
+		Some(first)
+	);
+
+	// Changing one of several formats should change the hash.
+	let format = ClipboardFormat::register("clipee hash test").expect("failed to register format");
+	handle
+		.set_raw(format, b"one")
+		.expect("failed to set raw data to clipboard");
+	let second = handle
+		.content_hash()
+		.expect("failed to hash clipboard")
+		.expect("clipboard has no contents");
+	assert_ne!(first, second);
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_string("hash me")
+		.expect("failed to set string to clipboard");
+	handle
+		.set_raw(format, b"two")
+		.expect("failed to set raw data to clipboard");
+	assert_ne!(
+		handle.content_hash().expect("failed to hash clipboard"),
+		Some(second)
+	);
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod common;
+
+use clipee_windows::{error::Result, history::ClipboardHistory, ClipboardHandle};
+use common::ClipboardLock;
+use std::time::{Duration, Instant};
+
+/// Changes the clipboard with a handle of its own, which closes the clipboard afterwards,
+/// as that's when the history hears about the change.
+fn change(change: impl FnOnce(&ClipboardHandle) -> Result<()>) {
+	// The history may still be reading the last change.
+	let deadline = Instant::now() + Duration::from_secs(2);
+	let handle = loop {
+		match ClipboardHandle::new() {
+			Ok(handle) => break handle,
+			Err(err) if Instant::now() > deadline => panic!("failed to open clipboard: {}", err),
+			Err(_) => std::thread::sleep(Duration::from_millis(20)),
+		}
+	};
+	change(&handle).expect("failed to change clipboard");
+}
+
+/// Puts the clipboard's contents back once the test is done, like [`ExclusiveClipboard`] would,
+/// but without keeping the clipboard open in the meantime.
+///
+/// [`ExclusiveClipboard`]: clipee_windows::exclusive::ExclusiveClipboard
+fn preserve_contents() -> impl Drop {
+	let snapshot = ClipboardHandle::new()
+		.and_then(|handle| handle.snapshot())
+		.expect("failed to snapshot clipboard");
+	scopeguard::guard(snapshot, |snapshot| {
+		change(|handle| handle.restore_snapshot(&snapshot));
+	})
+}
+
+/// Waits for the history thread to record the expected number of entries.
+fn wait_for_entries(history: &ClipboardHistory, count: usize) -> usize {
+	let deadline = Instant::now() + Duration::from_secs(2);
+	while history.entries().len() < count && Instant::now() < deadline {
+		std::thread::sleep(Duration::from_millis(20));
+	}
+	// Give it a moment longer, in case it's recording something it shouldn't.
+	std::thread::sleep(Duration::from_millis(250));
+	history.entries().len()
+}
+
+#[test]
+pub fn records_and_restores() {
+	let _lock = ClipboardLock::acquire();
+	let _contents = preserve_contents();
+	change(|handle| handle.set_string("first"));
+	// What's on the clipboard already is recorded when the history starts.
+	let history = ClipboardHistory::start(2).expect("failed to start history");
+	assert_eq!(wait_for_entries(&history, 1), 1);
+
+	change(|handle| handle.set_string("second"));
+	assert_eq!(wait_for_entries(&history, 2), 2);
+
+	// Setting the same contents again shouldn't be recorded twice.
+	change(|handle| handle.set_string("second"));
+	assert_eq!(wait_for_entries(&history, 3), 2);
+
+	// Contents marked as sensitive shouldn't be recorded at all.
+	change(|handle| handle.set_string_sensitive("password"));
+	assert_eq!(wait_for_entries(&history, 3), 2);
+
+	// Restoring an entry moves it to the front, rather than recording it again.
+	let older = history.get(1).expect("entry wasn't recorded");
+	assert!(history.restore(1).expect("failed to restore entry"));
+	assert_eq!(wait_for_entries(&history, 3), 2);
+	assert_eq!(
+		history.get(0).map(|entry| entry.content),
+		Some(older.content)
+	);
+	change(|handle| {
+		assert_eq!(handle.string_unicode()?, Some("first".to_owned()));
+		Ok(())
+	});
+	assert!(!history.restore(5).expect("failed to restore entry"));
+}
+
+#[test]
+pub fn skips_big_entries() {
+	let _lock = ClipboardLock::acquire();
+	let _contents = preserve_contents();
+	change(|handle| handle.set_string("x".repeat(4096)));
+	let history =
+		ClipboardHistory::start_with_max_entry_size(4, 1024).expect("failed to start history");
+	assert_eq!(wait_for_entries(&history, 1), 0);
+}
@@ -0,0 +1,331 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(feature = "image")]
+
+use clipee_windows::{exclusive::ExclusiveClipboard, format::ClipboardFormat, ClipboardHandle};
+use image::{GrayImage, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+
+/// A width of 3 forces row padding at 24 bits per pixel.
+const WIDTH: u32 = 3;
+const HEIGHT: u32 = 2;
+
+fn read_back(handle: &ClipboardHandle) -> RgbImage {
+	handle
+		.image()
+		.expect("failed to get image from clipboard")
+		.expect("image wasn't set in clipboard")
+}
+
+#[test]
+pub fn round_trip_rgb8() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbImage::from_fn(WIDTH, HEIGHT, |x, y| Rgb([x as u8 * 80, y as u8 * 120, 33]));
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_image(image.clone())
+		.expect("failed to set image to clipboard");
+	assert_eq!(
+		image,
+		read_back(&handle),
+		"RGB image didn't survive round-trip"
+	);
+}
+
+#[test]
+pub fn round_trip_rgba8() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+		Rgba([x as u8 * 80, y as u8 * 120, 33, 255])
+	});
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_image(image.clone())
+		.expect("failed to set image to clipboard");
+	let expected = image::DynamicImage::ImageRgba8(image).into_rgb8();
+	assert_eq!(
+		expected,
+		read_back(&handle),
+		"RGBA image didn't survive round-trip"
+	);
+}
+
+#[test]
+pub fn round_trip_luma8() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = GrayImage::from_fn(WIDTH, HEIGHT, |x, y| Luma([(x * 40 + y * 100) as u8]));
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_image(image.clone())
+		.expect("failed to set image to clipboard");
+	let expected = image::DynamicImage::ImageLuma8(image).into_rgb8();
+	assert_eq!(
+		expected,
+		read_back(&handle),
+		"Grayscale image didn't survive round-trip"
+	);
+}
+
+#[test]
+pub fn round_trip_transparent_rgba8() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+		Rgba([x as u8 * 80, y as u8 * 120, 33, (x * 100 + y * 27) as u8])
+	});
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_image_rgba(&image)
+		.expect("failed to set image to clipboard");
+	let result = handle
+		.image_rgba()
+		.expect("failed to get image from clipboard")
+		.expect("image wasn't set in clipboard");
+	assert_eq!(image, result, "RGBA image didn't survive round-trip");
+	// The plain CF_DIB should be flattened onto white.
+	assert_eq!(read_back(&handle).get_pixel(0, 0), &Rgb([255, 255, 255]));
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+	std::env::temp_dir().join(format!("clipee-{}-{}", std::process::id(), name))
+}
+
+#[test]
+pub fn bitmap_without_info() {
+	use windows::Win32::Graphics::Gdi::{CreateBitmap, DeleteObject};
+
+	let image = RgbImage::from_fn(WIDTH, HEIGHT, |x, y| Rgb([x as u8 * 80, y as u8 * 120, 33]));
+	// A 32-bit bitmap's rows are BGRX and top-down, and never need padding.
+	let pixels = image
+		.pixels()
+		.flat_map(|Rgb([r, g, b])| [*b, *g, *r, 0])
+		.collect::<Vec<u8>>();
+	let hbitmap = unsafe {
+		CreateBitmap(
+			WIDTH as i32,
+			HEIGHT as i32,
+			1,
+			32,
+			pixels.as_ptr() as *const _,
+		)
+	};
+	assert!(!hbitmap.is_invalid(), "failed to create bitmap");
+	scopeguard::defer! { unsafe { DeleteObject(hbitmap); } };
+	let result = clipee_windows::format::bitmap::get_without_info(hbitmap, None)
+		.expect("failed to get bitmap's pixels");
+	assert_eq!(image, result, "bitmap's pixels weren't read correctly");
+}
+
+#[test]
+pub fn bitfields_rgba_dib() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbImage::from_fn(WIDTH, HEIGHT, |x, y| Rgb([x as u8 * 80, y as u8 * 120, 33]));
+	// A BITMAPINFOHEADER for a top-down 32bpp BI_BITFIELDS DIB, followed by masks for RGBA rather than BGRA.
+	let mut dib = Vec::new();
+	dib.extend_from_slice(&40_u32.to_le_bytes());
+	dib.extend_from_slice(&(WIDTH as i32).to_le_bytes());
+	dib.extend_from_slice(&(-(HEIGHT as i32)).to_le_bytes());
+	dib.extend_from_slice(&1_u16.to_le_bytes());
+	dib.extend_from_slice(&32_u16.to_le_bytes());
+	dib.extend_from_slice(&3_u32.to_le_bytes()); // BI_BITFIELDS
+	dib.extend_from_slice(&(WIDTH * HEIGHT * 4).to_le_bytes());
+	dib.extend_from_slice(&[0; 16]);
+	for mask in [0x0000_00FF_u32, 0x0000_FF00, 0x00FF_0000] {
+		dib.extend_from_slice(&mask.to_le_bytes());
+	}
+	dib.extend(image.pixels().flat_map(|Rgb([r, g, b])| [*r, *g, *b, 255]));
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(ClipboardFormat::BitmapInfo, &dib)
+		.expect("failed to set raw data to clipboard");
+	let before = handle.dib_bytes().expect("failed to get CF_DIB");
+	assert_eq!(
+		image,
+		read_back(&handle),
+		"RGBA masked DIB wasn't read correctly"
+	);
+	// Reading it mustn't have changed what's on the clipboard.
+	assert_eq!(handle.dib_bytes().expect("failed to get CF_DIB"), before);
+}
+
+#[test]
+pub fn save_image_passthrough_png() {
+	use clipee_windows::format::{registered, ClipboardFormat};
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+		Rgba([x as u8 * 80, y as u8 * 120, 33, 128])
+	});
+	let mut png = Vec::new();
+	image::DynamicImage::ImageRgba8(image)
+		.write_to(
+			&mut std::io::Cursor::new(&mut png),
+			image::ImageOutputFormat::Png,
+		)
+		.expect("failed to encode png");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(
+			ClipboardFormat::register(registered::PNG).expect("failed to register format"),
+			&png,
+		)
+		.expect("failed to set png to clipboard");
+	let path = temp_path("passthrough.png");
+	scopeguard::defer! { let _ = std::fs::remove_file(&path); };
+	handle
+		.save_image(&path)
+		.expect("failed to save image")
+		.expect("image wasn't set in clipboard");
+	let saved = std::fs::read(&path).expect("failed to read saved image");
+	assert_eq!(saved, png, "PNG wasn't saved verbatim");
+}
+
+#[test]
+pub fn save_image_reencode() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbImage::from_fn(WIDTH, HEIGHT, |x, y| Rgb([x as u8 * 80, y as u8 * 120, 33]));
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_image(image.clone())
+		.expect("failed to set image to clipboard");
+	let path = temp_path("reencode.bmp");
+	scopeguard::defer! { let _ = std::fs::remove_file(&path); };
+	handle
+		.save_image(&path)
+		.expect("failed to save image")
+		.expect("image wasn't set in clipboard");
+	let saved = image::open(&path)
+		.expect("failed to open saved image")
+		.into_rgb8();
+	assert_eq!(image, saved, "Image didn't survive being saved");
+	assert_eq!(
+		handle.save_image(temp_path("unknown.xyz")),
+		Err(clipee_windows::error::Error::ImageExtension(temp_path(
+			"unknown.xyz"
+		)))
+	);
+}
+
+#[test]
+pub fn set_image_from_path() {
+	use clipee_windows::error::Error;
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbImage::from_fn(WIDTH, HEIGHT, |x, y| Rgb([x as u8 * 80, y as u8 * 120, 33]));
+	let path = temp_path("from_path.png");
+	scopeguard::defer! { let _ = std::fs::remove_file(&path); };
+	image.save(&path).expect("failed to save image");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_image_from_path(&path)
+		.expect("failed to set image to clipboard");
+	assert_eq!(image, read_back(&handle), "Image didn't survive round-trip");
+	let png = std::fs::read(&path).expect("failed to read image");
+	let raw = handle
+		.png()
+		.expect("failed to get png from clipboard")
+		.expect("png wasn't set in clipboard");
+	assert_eq!(raw, png, "PNG wasn't set verbatim");
+
+	match handle.set_image_from_path(temp_path("missing.png")) {
+		// The `image` crate's error is kept, rather than just its message.
+		Err(Error::OpenImage(err)) => assert!(matches!(*err, image::ImageError::IoError(_))),
+		result => panic!("unexpected result: {:?}", result),
+	}
+	let garbage = temp_path("garbage.png");
+	scopeguard::defer! { let _ = std::fs::remove_file(&garbage); };
+	std::fs::write(&garbage, b"this is not an image").expect("failed to write file");
+	assert!(matches!(
+		handle.set_image_from_path(&garbage),
+		Err(Error::DecodeImage(_))
+	));
+}
+
+#[test]
+pub fn setting_string_replaces_image() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_image(RgbImage::from_pixel(WIDTH, HEIGHT, Rgb([1, 2, 3])))
+		.expect("failed to set image to clipboard");
+	handle
+		.set_string("no more image")
+		.expect("failed to set string to clipboard");
+	let formats = handle
+		.available_formats()
+		.expect("failed to get available formats");
+	for format in [
+		ClipboardFormat::Bitmap,
+		ClipboardFormat::BitmapInfo,
+		ClipboardFormat::BitmapV5,
+	] {
+		assert!(
+			!formats.contains(&format),
+			"{} was left on the clipboard",
+			format
+		);
+	}
+	assert_eq!(
+		handle.image().expect("failed to get image from clipboard"),
+		None
+	);
+}
+
+#[test]
+pub fn dib_bytes() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_image(RgbImage::from_pixel(WIDTH, HEIGHT, Rgb([1, 2, 3])))
+		.expect("failed to set image to clipboard");
+	let dib = handle
+		.dib_bytes()
+		.expect("failed to get DIB from clipboard")
+		.expect("DIB wasn't set in clipboard");
+	// A BITMAPINFOHEADER starts with its own size, followed by the width and height.
+	assert_eq!(&dib[..4], &40_u32.to_le_bytes());
+	assert_eq!(&dib[4..8], &(WIDTH as i32).to_le_bytes());
+	assert_eq!(&dib[8..12], &(HEIGHT as i32).to_le_bytes());
+}
+
+#[test]
+pub fn bitmap_size() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_image(RgbImage::from_pixel(WIDTH, HEIGHT, Rgb([1, 2, 3])))
+		.expect("failed to set image to clipboard");
+	let dib = handle
+		.dib_bytes()
+		.expect("failed to get DIB from clipboard")
+		.expect("DIB wasn't set in clipboard");
+	assert_eq!(
+		handle
+			.format_size(ClipboardFormat::BitmapInfo)
+			.expect("failed to get DIB size"),
+		Some(dib.len())
+	);
+	// CF_BITMAP is synthesized in whatever depth the screen is, so its size can only be bounded.
+	let bitmap_size = handle
+		.format_size(ClipboardFormat::Bitmap)
+		.expect("failed to get bitmap size")
+		.expect("bitmap wasn't synthesized");
+	assert!(bitmap_size >= (WIDTH * HEIGHT) as usize);
+}
+
+#[cfg(feature = "arboard-compat")]
+#[test]
+pub fn round_trip_arboard() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let image = RgbaImage::from_fn(4, 3, |x, y| Rgba([x as u8 * 60, y as u8 * 100, 200, 255]));
+	handle
+		.set_image_arboard(clipee_windows::format::arboard_compat::to_image_data(
+			image.clone(),
+		))
+		.expect("failed to set image to clipboard");
+	let data = handle
+		.image_arboard()
+		.expect("failed to get image from clipboard")
+		.expect("image wasn't set in clipboard");
+	assert_eq!((data.width, data.height), (4, 3));
+	assert_eq!(
+		data.bytes.as_ref(),
+		image.as_raw().as_slice(),
+		"image didn't survive the round-trip"
+	);
+}
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{error::Error, exclusive::ExclusiveClipboard, ClipboardHandle};
+
+#[test]
+pub fn max_read_size() {
+	let exclusive =
+		ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let handle = ClipboardHandle::builder()
+		.max_read_size(16)
+		.build()
+		.expect("failed to open clipboard");
+	assert_eq!(handle.max_read_size(), Some(16));
+	assert_eq!(handle.clone().max_read_size(), Some(16));
+	// Other handles don't pick up the limit.
+	assert_eq!(
+		ClipboardHandle::new()
+			.expect("failed to open clipboard")
+			.max_read_size(),
+		None
+	);
+	assert_eq!(exclusive.max_read_size(), None);
+	handle
+		.set_string("this string is definitely more than sixteen bytes long")
+		.expect("failed to set string to clipboard");
+	match handle.string_unicode() {
+		Err(Error::PayloadTooLarge { size, limit }) => {
+			assert_eq!(limit, 16);
+			assert!(size > limit);
+		}
+		other => panic!("expected the read to be rejected, got {:?}", other),
+	}
+}
+
+#[test]
+pub fn huge_string() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	// 50 million UTF-16 code units, which is about 100 MB on the clipboard.
+	let string = "clipee ".repeat(50_000_000 / 7);
+	handle
+		.set_string(&string)
+		.expect("failed to set string to clipboard");
+	let read = handle
+		.string_unicode()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert!(read == string, "huge string didn't survive round-trip");
+}
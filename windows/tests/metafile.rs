@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{exclusive::ExclusiveClipboard, format::ClipboardFormat};
+
+/// Builds the smallest valid EMF: a header record followed by an end-of-file record.
+fn empty_emf() -> Vec<u8> {
+	let mut emf = Vec::new();
+	let mut push = |values: &[u32]| {
+		values
+			.iter()
+			.for_each(|value| emf.extend_from_slice(&value.to_le_bytes()))
+	};
+	// EMR_HEADER
+	push(&[1, 88]);
+	push(&[0, 0, 100, 100]); // rclBounds
+	push(&[0, 0, 2646, 2646]); // rclFrame
+	push(&[0x464D_4520, 0x0001_0000, 108, 2]); // dSignature, nVersion, nBytes, nRecords
+	push(&[1]); // nHandles + sReserved
+	push(&[0, 0, 0]); // nDescription, offDescription, nPalEntries
+	push(&[1920, 1080, 508, 286]); // szlDevice, szlMillimeters
+	push(&[14, 20, 0, 16, 20]); // EMR_EOF
+	emf
+}
+
+#[test]
+pub fn round_trip_enh_metafile() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let emf = empty_emf();
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_enh_metafile(&emf)
+		.expect("failed to set metafile to clipboard");
+	let result = handle
+		.enh_metafile()
+		.expect("failed to get metafile from clipboard")
+		.expect("metafile wasn't set in clipboard");
+	assert_eq!(emf, result, "Metafile didn't survive round-trip");
+}
+
+#[test]
+pub fn enh_metafile_size() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let emf = empty_emf();
+	handle
+		.set_enh_metafile(&emf)
+		.expect("failed to set metafile to clipboard");
+	let size = handle
+		.format_size(ClipboardFormat::EnhMetafile)
+		.expect("failed to get metafile size");
+	let metafile = handle
+		.enh_metafile()
+		.expect("failed to get metafile from clipboard")
+		.expect("metafile wasn't set in clipboard");
+	assert_eq!(size, Some(metafile.len()));
+}
@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{
+	exclusive::ExclusiveClipboard,
+	policy::{CloudPolicy, HistoryPolicy},
+};
+
+#[test]
+pub fn history_and_cloud_policy() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_string("policy test")
+		.expect("failed to set string to clipboard");
+	assert_eq!(
+		handle.history_allowed().expect("failed to read policy"),
+		None
+	);
+	assert_eq!(handle.cloud_allowed().expect("failed to read policy"), None);
+
+	handle
+		.set_history_policy(HistoryPolicy::Deny)
+		.expect("failed to set history policy");
+	handle
+		.set_cloud_policy(CloudPolicy::Allow)
+		.expect("failed to set cloud policy");
+	assert_eq!(
+		handle.history_allowed().expect("failed to read policy"),
+		Some(false)
+	);
+	assert_eq!(
+		handle.cloud_allowed().expect("failed to read policy"),
+		Some(true)
+	);
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{error::Error, exclusive::ExclusiveClipboard};
+
+#[test]
+pub fn commit() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_string("before")
+		.expect("failed to set string to clipboard");
+	let mut session = handle.session().expect("failed to start session");
+	assert_eq!(
+		session
+			.string()
+			.expect("failed to get string from clipboard"),
+		Some("before".to_owned())
+	);
+	session
+		.set_string("after")
+		.expect("failed to set string in session");
+	assert_eq!(
+		session
+			.string()
+			.expect("failed to get string from clipboard"),
+		Some("before".to_owned()),
+		"session wrote to the clipboard before being committed"
+	);
+	session.commit().expect("failed to commit session");
+	assert_eq!(
+		handle
+			.string()
+			.expect("failed to get string from clipboard"),
+		Some("after".to_owned())
+	);
+}
+
+#[test]
+pub fn abort() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_string("untouched")
+		.expect("failed to set string to clipboard");
+	let mut session = handle.session().expect("failed to start session");
+	session
+		.set_string("aborted")
+		.expect("failed to set string in session");
+	session.abort();
+	let mut session = handle.session().expect("failed to start session");
+	session
+		.set_string("dropped")
+		.expect("failed to set string in session");
+	drop(session);
+	assert_eq!(
+		handle
+			.string()
+			.expect("failed to get string from clipboard"),
+		Some("untouched".to_owned())
+	);
+}
+
+#[test]
+pub fn nested_session() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let session = handle.session().expect("failed to start session");
+	assert_eq!(
+		handle.session().err(),
+		Some(Error::ClipboardAlreadyOpen),
+		"second session should be rejected"
+	);
+	drop(session);
+	handle
+		.session()
+		.expect("failed to start session after the first one ended");
+}
+
+#[test]
+pub fn interior_nul() {
+	use clipee_windows::format::string::NulPolicy;
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let mut session = handle.session().expect("failed to start session");
+	assert_eq!(
+		session.set_string("ab\0cd"),
+		Err(Error::InteriorNul { position: 2 })
+	);
+	session
+		.set_string_with_nul_policy("ab\0cd", NulPolicy::ReplaceWith(' '))
+		.expect("failed to set string in session");
+	session.commit().expect("failed to commit session");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		Some("ab cd".to_owned())
+	);
+}
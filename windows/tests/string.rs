@@ -1,12 +1,16 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use clipee_windows::ClipboardHandle;
+use clipee_windows::{error::Error, exclusive::ExclusiveClipboard, format::ClipboardFormat};
+use std::{
+	ffi::OsString,
+	os::windows::ffi::{OsStrExt, OsStringExt},
+};
 
 static UTF8_TEST: &str = include_str!("utf8.txt");
 
 #[test]
 pub fn round_trip_string() {
-	let handle = ClipboardHandle::new().expect("failed to open clipboard");
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
 	let result = handle.set_string(UTF8_TEST);
 	assert!(
 		result.is_ok(),
@@ -24,3 +28,330 @@ pub fn round_trip_string() {
 		.expect("failed to get string from clipboard");
 	assert_eq!(UTF8_TEST, result, "String didn't survive round-trip!");
 }
+
+#[test]
+pub fn round_trip_astral_string() {
+	// Characters outside the BMP are encoded as surrogate pairs in UTF-16.
+	let astral = "🦀 𝔘𝔫𝔦𝔠𝔬𝔡𝔢 𐍈 a\u{10FFFF}z";
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_string(astral)
+		.expect("failed to set string to clipboard");
+	let result = handle
+		.string_unicode()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(astral, result, "Astral string didn't survive round-trip!");
+}
+
+#[test]
+pub fn sensitive_string_markers() {
+	use clipee_windows::format::{registered, ClipboardFormat};
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_string_sensitive("hunter2")
+		.expect("failed to set string to clipboard");
+	for name in [
+		registered::EXCLUDE_CLIPBOARD_CONTENT_FROM_MONITOR_PROCESSING,
+		registered::CAN_INCLUDE_IN_CLIPBOARD_HISTORY,
+		registered::CAN_UPLOAD_TO_CLOUD_CLIPBOARD,
+	] {
+		let format = ClipboardFormat::register(name).expect("failed to register format");
+		let data = handle
+			.get_raw(format)
+			.expect("failed to get marker from clipboard")
+			.unwrap_or_else(|| panic!("{} wasn't set in clipboard", name));
+		assert_eq!(&data[..4], &[0, 0, 0, 0], "{} has the wrong payload", name);
+	}
+	let result = handle
+		.string_unicode()
+		.expect("failed to get string from clipboard");
+	assert_eq!(result.as_deref(), Some("hunter2"));
+}
+
+#[test]
+pub fn unpaired_surrogate() {
+	let wide = [0x61, 0xD800, 0x62];
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(
+			ClipboardFormat::UnicodeText,
+			&[0x61, 0, 0x00, 0xD8, 0x62, 0, 0, 0],
+		)
+		.expect("failed to set raw data to clipboard");
+	let os_string = handle
+		.string_unicode_os()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(os_string.encode_wide().collect::<Vec<_>>(), wide);
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard")
+			.as_deref(),
+		Some("a\u{FFFD}b")
+	);
+
+	handle
+		.set_string_os(OsString::from_wide(&wide))
+		.expect("failed to set string to clipboard");
+	let os_string = handle
+		.string_unicode_os()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(os_string.encode_wide().collect::<Vec<_>>(), wide);
+}
+
+#[test]
+pub fn legacy_code_page_string() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	// "café" in Windows-1252, where é is a single byte that's invalid on its own in UTF-8.
+	handle
+		.set_raw(ClipboardFormat::Text, b"caf\xE9\0")
+		.expect("failed to set raw data to clipboard");
+	assert!(matches!(handle.string(), Err(Error::InvalidString(_))));
+	let lossy = handle
+		.string_lossy()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(lossy.string, "caf\u{FFFD}");
+	assert_eq!(lossy.bytes, b"caf\xE9");
+	assert!(lossy.is_lossy());
+
+	handle
+		.set_raw(ClipboardFormat::Text, b"cafe\0")
+		.expect("failed to set raw data to clipboard");
+	assert_eq!(
+		handle
+			.string()
+			.expect("failed to get string from clipboard")
+			.as_deref(),
+		Some("cafe")
+	);
+	let lossy = handle
+		.string_lossy()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert!(!lossy.is_lossy());
+}
+
+#[test]
+pub fn byte_order_mark() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(
+			ClipboardFormat::UnicodeText,
+			&[0xFF, 0xFE, 0x68, 0, 0x69, 0, 0, 0],
+		)
+		.expect("failed to set raw data to clipboard");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard")
+			.as_deref(),
+		Some("hi")
+	);
+	assert_eq!(
+		handle
+			.string_unicode_raw()
+			.expect("failed to get string from clipboard")
+			.as_deref(),
+		Some("\u{FEFF}hi")
+	);
+}
+
+#[test]
+pub fn round_trip_url() {
+	use clipee_windows::format::registered;
+
+	let url = "https://github.com/Absolucy/clipee";
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.set_url(url).expect("failed to set URL to clipboard");
+	assert_eq!(
+		handle
+			.url()
+			.expect("failed to get URL from clipboard")
+			.as_deref(),
+		Some(url)
+	);
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard")
+			.as_deref(),
+		Some(url)
+	);
+	let formats = handle
+		.available_formats()
+		.expect("failed to get available formats");
+	for format in [
+		ClipboardFormat::UnicodeText,
+		ClipboardFormat::register(registered::URL_WIDE).expect("failed to register format"),
+		ClipboardFormat::register(registered::URL).expect("failed to register format"),
+	] {
+		assert!(
+			formats.contains(&format),
+			"{} wasn't set in clipboard",
+			format
+		);
+	}
+	assert_eq!(handle.set_url(""), Err(Error::EmptyUrl));
+
+	// The ANSI format only gets ASCII.
+	let url = "https://example.com/smörgåsbord";
+	handle.set_url(url).expect("failed to set URL to clipboard");
+	assert_eq!(
+		handle
+			.url()
+			.expect("failed to get URL from clipboard")
+			.as_deref(),
+		Some(url)
+	);
+	let ansi = handle
+		.get_raw(ClipboardFormat::register(registered::URL).expect("failed to register format"))
+		.expect("failed to get raw data from clipboard")
+		.expect("URL wasn't set in clipboard");
+	assert!(ansi.starts_with(b"https://example.com/sm%C3%B6rg%C3%A5sbord\0"));
+}
+
+#[test]
+pub fn text_from_html() {
+	use clipee_windows::format::{html, registered};
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let data = html::encode(
+		"<html><body>",
+		"<p>Fish &amp; chips</p><p>\u{e9}clair</p>",
+		"</body></html>",
+	);
+	let format = ClipboardFormat::register(registered::HTML).expect("failed to register format");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_raw(format, &data)
+		.expect("failed to set raw data to clipboard");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		None
+	);
+	assert_eq!(
+		handle
+			.text_any()
+			.expect("failed to get text from clipboard"),
+		Some("Fish & chips\n\u{e9}clair".to_string())
+	);
+	// Plain text wins when there is some.
+	handle
+		.set_string("plain")
+		.expect("failed to set string to clipboard");
+	assert_eq!(
+		handle
+			.text_any()
+			.expect("failed to get text from clipboard"),
+		Some("plain".to_string())
+	);
+}
+
+#[test]
+pub fn interior_nul() {
+	use clipee_windows::format::string::NulPolicy;
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle
+		.set_string("before")
+		.expect("failed to set string to clipboard");
+	// By default, the string is rejected, and the clipboard is left alone.
+	assert_eq!(
+		handle.set_string("ab\0cd\0ef"),
+		Err(Error::InteriorNul { position: 2 })
+	);
+	assert_eq!(
+		handle
+			.set_string_with_nul_policy("ab\0cd\0ef", NulPolicy::Error)
+			.unwrap_err(),
+		Error::InteriorNul { position: 2 }
+	);
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		Some("before".to_string())
+	);
+	handle
+		.set_string_with_nul_policy("ab\0cd\0ef", NulPolicy::Truncate)
+		.expect("failed to set string to clipboard");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		Some("ab".to_string())
+	);
+	handle
+		.set_string_with_nul_policy("ab\0cd\0ef", NulPolicy::ReplaceWith('\u{FFFD}'))
+		.expect("failed to set string to clipboard");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		Some("ab\u{FFFD}cd\u{FFFD}ef".to_string())
+	);
+	// Replacing NULs with NULs would still cut the string off, so it's rejected too.
+	assert_eq!(
+		handle.set_string_with_nul_policy("ab\0cd", NulPolicy::ReplaceWith('\0')),
+		Err(Error::InteriorNul { position: 2 })
+	);
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		Some("ab\u{FFFD}cd\u{FFFD}ef".to_string())
+	);
+}
+
+#[test]
+pub fn interior_nul_os() {
+	use clipee_windows::format::string::NulPolicy;
+
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	// An unpaired surrogate, then a NUL, which is at index 2 in UTF-16 code units.
+	let wide = [0x61, 0xD800, 0, 0x62];
+	assert_eq!(
+		handle.set_string_os(OsString::from_wide(&wide)),
+		Err(Error::InteriorNul { position: 2 })
+	);
+	handle
+		.set_string_os_with_nul_policy(OsString::from_wide(&wide), NulPolicy::Truncate)
+		.expect("failed to set string to clipboard");
+	let os_string = handle
+		.string_unicode_os()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(os_string.encode_wide().collect::<Vec<_>>(), [0x61, 0xD800]);
+	// A replacement outside the BMP takes up two code units.
+	handle
+		.set_string_os_with_nul_policy(
+			OsString::from_wide(&wide),
+			NulPolicy::ReplaceWith('\u{1F41F}'),
+		)
+		.expect("failed to set string to clipboard");
+	let os_string = handle
+		.string_unicode_os()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(
+		os_string.encode_wide().collect::<Vec<_>>(),
+		[0x61, 0xD800, 0xD83D, 0xDC1F, 0x62]
+	);
+	assert_eq!(
+		handle.set_string_os_with_nul_policy(
+			OsString::from_wide(&wide),
+			NulPolicy::ReplaceWith('\0')
+		),
+		Err(Error::InteriorNul { position: 2 })
+	);
+}
@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod common;
+
+use clipee_windows::{error::Error, ClipboardHandle};
+use common::{message_window, wide, ClipboardLock};
+use std::{
+	sync::mpsc,
+	time::{Duration, Instant},
+};
+use windows::{
+	core::PCWSTR,
+	Win32::{
+		Foundation::HANDLE,
+		System::DataExchange::{
+			CloseClipboard, EmptyClipboard, OpenClipboard, RegisterClipboardFormatW,
+			SetClipboardData,
+		},
+		UI::WindowsAndMessaging::DestroyWindow,
+	},
+};
+
+#[test]
+pub fn hung_owner() {
+	let _lock = ClipboardLock::acquire();
+	let format_name = wide("clipee hung owner test");
+	let format = unsafe { RegisterClipboardFormatW(PCWSTR(format_name.as_ptr())) };
+	let (ready, wait_ready) = mpsc::channel();
+	let (finish, wait_finish) = mpsc::channel::<()>();
+	// This thread promises a format, then doesn't process any messages, so it never renders it.
+	let owner = std::thread::spawn(move || {
+		let window = message_window();
+		unsafe {
+			assert!(OpenClipboard(window).as_bool(), "failed to open clipboard");
+			EmptyClipboard();
+			SetClipboardData(format, HANDLE::default());
+			CloseClipboard();
+		}
+		ready.send(()).expect("failed to signal readiness");
+		let _ = wait_finish.recv();
+		unsafe { DestroyWindow(window) };
+	});
+	wait_ready.recv().expect("owner thread failed");
+	let handle = ClipboardHandle::builder()
+		.read_timeout(Duration::from_millis(200))
+		.build()
+		.expect("failed to open clipboard");
+	let format = clipee_windows::format::ClipboardFormat::try_from_u32(format)
+		.expect("format wasn't registered");
+	let start = Instant::now();
+	assert_eq!(handle.get_raw(format), Err(Error::Timeout));
+	assert!(
+		start.elapsed() < Duration::from_secs(5),
+		"read took {:?} to time out",
+		start.elapsed()
+	);
+	// The clipboard is still open with the handle, which can carry on with anything that doesn't need the owner.
+	assert!(handle.is_valid(), "handle was invalidated by the timeout");
+	assert!(handle
+		.available_formats()
+		.expect("failed to get available formats")
+		.contains(&format));
+	assert_eq!(handle.format_size(format), Err(Error::Timeout));
+	finish.send(()).expect("failed to tell owner to finish");
+	owner.join().expect("owner thread panicked");
+
+	// With the owner gone, the handle can take over the clipboard.
+	handle
+		.set_string("after timeout")
+		.expect("failed to set string to clipboard");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard")
+			.as_deref(),
+		Some("after timeout")
+	);
+}
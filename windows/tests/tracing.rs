@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(feature = "tracing")]
+
+use clipee_windows::exclusive::ExclusiveClipboard;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tracing::{
+	span::{Attributes, Id, Record},
+	Event, Metadata, Subscriber,
+};
+
+/// A span's name, and the name of the span it was created in.
+type SpanName = (&'static str, Option<&'static str>);
+
+/// Records the name of every span, along with the name of the span it was created in.
+#[derive(Clone, Default)]
+struct SpanRecorder {
+	spans: Arc<Mutex<Vec<SpanName>>>,
+	stack: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Subscriber for SpanRecorder {
+	fn enabled(&self, _: &Metadata<'_>) -> bool {
+		true
+	}
+
+	fn new_span(&self, span: &Attributes<'_>) -> Id {
+		let mut spans = self.spans.lock();
+		let parent = self.stack.lock().last().map(|&id| spans[id as usize - 1].0);
+		spans.push((span.metadata().name(), parent));
+		Id::from_u64(spans.len() as u64)
+	}
+
+	fn record(&self, _: &Id, _: &Record<'_>) {}
+
+	fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+	fn event(&self, _: &Event<'_>) {}
+
+	fn enter(&self, span: &Id) {
+		self.stack.lock().push(span.into_u64());
+	}
+
+	fn exit(&self, _: &Id) {
+		self.stack.lock().pop();
+	}
+}
+
+#[test]
+pub fn round_trip_spans() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let recorder = SpanRecorder::default();
+	tracing::subscriber::with_default(recorder.clone(), || {
+		handle
+			.set_string("traced")
+			.expect("failed to set string to clipboard");
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard")
+			.expect("string wasn't set in clipboard");
+	});
+	assert_eq!(
+		*recorder.spans.lock(),
+		[
+			("set_string", None),
+			("empty", Some("set_string")),
+			("string_unicode", None),
+		]
+	);
+}
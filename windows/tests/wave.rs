@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clipee_windows::{error::Error, exclusive::ExclusiveClipboard};
+
+/// Builds a WAV file containing a few samples of 8-bit mono audio.
+fn tiny_wave() -> Vec<u8> {
+	let samples = [0x80_u8, 0xFF, 0x80, 0x00];
+	let mut wave = Vec::new();
+	wave.extend_from_slice(b"RIFF");
+	wave.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+	wave.extend_from_slice(b"WAVEfmt ");
+	wave.extend_from_slice(&16_u32.to_le_bytes());
+	wave.extend_from_slice(&1_u16.to_le_bytes()); // PCM
+	wave.extend_from_slice(&1_u16.to_le_bytes()); // mono
+	wave.extend_from_slice(&8000_u32.to_le_bytes()); // sample rate
+	wave.extend_from_slice(&8000_u32.to_le_bytes()); // byte rate
+	wave.extend_from_slice(&1_u16.to_le_bytes()); // block align
+	wave.extend_from_slice(&8_u16.to_le_bytes()); // bits per sample
+	wave.extend_from_slice(b"data");
+	wave.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+	wave.extend_from_slice(&samples);
+	wave
+}
+
+#[test]
+pub fn round_trip_wave() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	let wave = tiny_wave();
+	handle
+		.set_wave(&wave)
+		.expect("failed to set audio to clipboard");
+	let result = handle
+		.wave()
+		.expect("failed to get audio from clipboard")
+		.expect("audio wasn't set in clipboard");
+	assert_eq!(wave, result, "Audio didn't survive round-trip");
+}
+
+#[test]
+pub fn reject_invalid_wave() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	assert_eq!(handle.set_wave(b"not a wave file"), Err(Error::InvalidWave));
+}
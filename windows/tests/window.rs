@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod common;
+
+use clipee_windows::{
+	error::Error, exclusive::ExclusiveClipboard, format::ClipboardFormat, ClipboardHandle,
+};
+use common::{message_window, ClipboardLock};
+use std::sync::{
+	atomic::{AtomicBool, AtomicUsize, Ordering},
+	Arc,
+};
+use windows::Win32::{
+	System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard},
+	UI::WindowsAndMessaging::DestroyWindow,
+};
+
+#[test]
+pub fn delayed_rendering() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	let format =
+		ClipboardFormat::register("clipee delayed test").expect("failed to register format");
+	let renders = Arc::new(AtomicUsize::new(0));
+	handle
+		.set_delayed(format, {
+			let renders = renders.clone();
+			move || {
+				renders.fetch_add(1, Ordering::SeqCst);
+				b"rendered later".to_vec()
+			}
+		})
+		.expect("failed to promise data");
+	assert_eq!(renders.load(Ordering::SeqCst), 0);
+	assert_eq!(
+		handle.get_raw(format).expect("failed to get raw data"),
+		Some(b"rendered later".to_vec())
+	);
+	// Once rendered, the data stays on the clipboard.
+	assert_eq!(
+		handle.get_raw(format).expect("failed to get raw data"),
+		Some(b"rendered later".to_vec())
+	);
+	assert_eq!(renders.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+pub fn ownership_lost() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	handle.empty().expect("failed to empty clipboard");
+	handle
+		.set_string("owned")
+		.expect("failed to set string to clipboard");
+	let lost = Arc::new(AtomicBool::new(false));
+	handle.on_ownership_lost({
+		let lost = lost.clone();
+		move || lost.store(true, Ordering::SeqCst)
+	});
+	// Emptying the clipboard ourselves keeps it ours.
+	handle.empty().expect("failed to empty clipboard");
+	handle.process_messages();
+	assert!(!lost.load(Ordering::SeqCst));
+
+	// Another window emptying it sends WM_DESTROYCLIPBOARD to us, as the owner. It's on this thread,
+	// so it's sent straight to our window procedure, and the clipboard is reopened with our window afterwards.
+	let other = message_window();
+	unsafe {
+		CloseClipboard();
+		assert!(OpenClipboard(other).as_bool(), "failed to open clipboard");
+		EmptyClipboard();
+		CloseClipboard();
+		DestroyWindow(other);
+		assert!(
+			OpenClipboard(handle.window()).as_bool(),
+			"failed to reopen clipboard"
+		);
+	}
+	handle.process_messages();
+	assert!(lost.load(Ordering::SeqCst));
+}
+
+#[test]
+pub fn render_all_formats() {
+	// The handle has to be the last one, so that dropping it destroys its window.
+	let _lock = ClipboardLock::acquire();
+	let snapshot = ClipboardHandle::new()
+		.and_then(|handle| handle.snapshot())
+		.expect("failed to snapshot clipboard");
+	let format =
+		ClipboardFormat::register("clipee render all test").expect("failed to register format");
+	let renders = Arc::new(AtomicUsize::new(0));
+	{
+		let handle = ClipboardHandle::new().expect("failed to open clipboard");
+		handle.empty().expect("failed to empty clipboard");
+		handle
+			.set_delayed(format, {
+				let renders = renders.clone();
+				move || {
+					renders.fetch_add(1, Ordering::SeqCst);
+					b"rendered on exit".to_vec()
+				}
+			})
+			.expect("failed to promise data");
+	}
+	// The window was sent WM_RENDERALLFORMATS as it was destroyed, so the promise was kept.
+	assert_eq!(renders.load(Ordering::SeqCst), 1);
+	let handle = ClipboardHandle::new().expect("failed to open clipboard");
+	let data = handle.get_raw(format);
+	handle
+		.restore_snapshot(&snapshot)
+		.expect("failed to restore snapshot");
+	assert_eq!(
+		data.expect("failed to get raw data"),
+		Some(b"rendered on exit".to_vec())
+	);
+}
+
+#[test]
+pub fn with_window() {
+	let handle = ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	assert_eq!(
+		ClipboardHandle::with_window(0x1234).err(),
+		Some(Error::InvalidWindow(0x1234)),
+		"garbage window handle should be rejected"
+	);
+	let same = ClipboardHandle::with_window(handle.window().0)
+		.expect("failed to get handle with the same window");
+	assert_eq!(same.window(), handle.window());
+}
+
+#[test]
+pub fn refresh_after_window_destroyed() {
+	// The exclusive handle itself is refreshed, so that it can still restore its snapshot afterwards.
+	let mut handle =
+		ExclusiveClipboard::acquire_with_snapshot().expect("failed to acquire clipboard");
+	assert!(handle.is_valid());
+	unsafe { windows::Win32::UI::WindowsAndMessaging::DestroyWindow(handle.window()) };
+	assert!(!handle.is_valid());
+	handle
+		.refresh()
+		.expect("failed to refresh clipboard handle");
+	assert!(handle.is_valid());
+	handle
+		.set_string("refreshed")
+		.expect("failed to set string to clipboard");
+	assert_eq!(
+		handle
+			.string_unicode()
+			.expect("failed to get string from clipboard"),
+		Some("refreshed".to_string())
+	);
+	// Other handles pick up the refreshed one.
+	let other = ClipboardHandle::new().expect("failed to open clipboard");
+	assert_eq!(other.window(), handle.window());
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{error::Result, format::X11ClipboardFormat};
+use x11rb::{
+	connection::{Connection, RequestConnection},
+	protocol::xproto::{Atom, ConnectionExt, CreateWindowAux, EventMask, Window, WindowClass},
+	rust_connection::RustConnection,
+	COPY_DEPTH_FROM_PARENT,
+};
+
+x11rb::atom_manager! {
+	pub(crate) Atoms: AtomsCookie {
+		CLIPBOARD,
+		TARGETS,
+		INCR,
+		UTF8_STRING,
+		PLAIN_TEXT_UTF8: b"text/plain;charset=utf-8",
+		URI_LIST: b"text/uri-list",
+		PNG: b"image/png",
+		CLIPEE_SELECTION,
+	}
+}
+
+/// A connection to the X server, along with a hidden window to send and receive selection events with.
+pub(crate) struct Context {
+	pub conn: RustConnection,
+	pub window: Window,
+	pub atoms: Atoms,
+}
+
+impl Context {
+	pub fn new() -> Result<Self> {
+		let (conn, screen_num) = RustConnection::connect(None)?;
+		let screen = &conn.setup().roots[screen_num];
+		let window = conn.generate_id()?;
+		conn.create_window(
+			COPY_DEPTH_FROM_PARENT,
+			window,
+			screen.root,
+			0,
+			0,
+			1,
+			1,
+			0,
+			WindowClass::INPUT_OUTPUT,
+			screen.root_visual,
+			// Incremental transfers are driven by property changes.
+			&CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+		)?;
+		let atoms = Atoms::new(&conn)?.reply()?;
+		conn.flush()?;
+		Ok(Self {
+			conn,
+			window,
+			atoms,
+		})
+	}
+
+	/// Gets the atom for a format, interning it if needed.
+	pub fn atom(&self, format: &X11ClipboardFormat) -> Result<Atom> {
+		Ok(match format {
+			X11ClipboardFormat::Utf8String => self.atoms.UTF8_STRING,
+			X11ClipboardFormat::PlainTextUtf8 => self.atoms.PLAIN_TEXT_UTF8,
+			X11ClipboardFormat::UriList => self.atoms.URI_LIST,
+			X11ClipboardFormat::Png => self.atoms.PNG,
+			X11ClipboardFormat::Other(name) => {
+				self.conn.intern_atom(false, name.as_bytes())?.reply()?.atom
+			}
+		})
+	}
+
+	/// Gets the format that an atom refers to.
+	pub fn format(&self, atom: Atom) -> Result<X11ClipboardFormat> {
+		let name = self.conn.get_atom_name(atom)?.reply()?.name;
+		Ok(X11ClipboardFormat::from_name(&String::from_utf8_lossy(
+			&name,
+		)))
+	}
+
+	/// The most data that fits in a single property change.
+	pub fn max_chunk_size(&self) -> usize {
+		// Leave plenty of room for the request header.
+		self.conn.maximum_request_bytes() / 4
+	}
+}
+
+impl Drop for Context {
+	fn drop(&mut self) {
+		let _ = self.conn.destroy_window(self.window);
+		let _ = self.conn.flush();
+	}
+}
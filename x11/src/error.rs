@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::format::X11ClipboardFormat;
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+	#[error("Failed to connect to the X server: {0}")]
+	Connect(String),
+	#[error("Lost connection to the X server: {0}")]
+	Connection(String),
+	#[error("X server rejected a request: {0}")]
+	Request(String),
+	#[error("Timed out waiting for {format:?} clipboard data")]
+	Timeout { format: X11ClipboardFormat },
+	#[error("Failed to take ownership of the clipboard selection")]
+	SelectionOwnership,
+	#[error("Failed to decode string as UTF-8: {0}")]
+	InvalidString(std::str::Utf8Error),
+	#[error("{0:?} is not a valid file URI")]
+	FileUri(String),
+	#[error("A valid image could not be constructed from the clipboard data")]
+	InvalidImage,
+}
+
+// x11rb's errors can't be cloned or compared, so only their messages are kept.
+
+impl From<ConnectError> for Error {
+	fn from(err: ConnectError) -> Self {
+		Self::Connect(err.to_string())
+	}
+}
+
+impl From<ConnectionError> for Error {
+	fn from(err: ConnectionError) -> Self {
+		Self::Connection(err.to_string())
+	}
+}
+
+impl From<ReplyError> for Error {
+	fn from(err: ReplyError) -> Self {
+		match err {
+			ReplyError::ConnectionError(err) => err.into(),
+			ReplyError::X11Error(err) => Self::Request(format!("{:?}", err)),
+		}
+	}
+}
+
+impl From<ReplyOrIdError> for Error {
+	fn from(err: ReplyOrIdError) -> Self {
+		match err {
+			ReplyOrIdError::ConnectionError(err) => err.into(),
+			other => Self::Request(other.to_string()),
+		}
+	}
+}
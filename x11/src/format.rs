@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+pub(crate) mod uri_list;
+
+use std::{
+	convert::Infallible,
+	fmt::{self, Display},
+	str::FromStr,
+};
+
+/// A selection target, identified by its atom name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum X11ClipboardFormat {
+	/// `UTF8_STRING`, the usual target for text.
+	Utf8String,
+	/// `text/plain;charset=utf-8`, which some toolkits offer instead of `UTF8_STRING`.
+	PlainTextUtf8,
+	/// `text/uri-list`, a list of URIs, one per line.
+	UriList,
+	/// `image/png`.
+	Png,
+	/// Any other target, by its atom name.
+	Other(String),
+}
+
+impl X11ClipboardFormat {
+	/// Gets the format for an atom name.
+	pub fn from_name(name: &str) -> Self {
+		match name {
+			"UTF8_STRING" => Self::Utf8String,
+			"text/plain;charset=utf-8" => Self::PlainTextUtf8,
+			"text/uri-list" => Self::UriList,
+			"image/png" => Self::Png,
+			other => Self::Other(other.to_owned()),
+		}
+	}
+
+	/// Returns the atom name of this format.
+	pub fn name(&self) -> &str {
+		match self {
+			Self::Utf8String => "UTF8_STRING",
+			Self::PlainTextUtf8 => "text/plain;charset=utf-8",
+			Self::UriList => "text/uri-list",
+			Self::Png => "image/png",
+			Self::Other(name) => name,
+		}
+	}
+}
+
+impl Display for X11ClipboardFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+impl FromStr for X11ClipboardFormat {
+	type Err = Infallible;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Ok(Self::from_name(name))
+	}
+}
@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+pub(crate) mod context;
+pub mod error;
+pub mod format;
+pub(crate) mod server;
+
+use self::{
+	context::Context,
+	error::{Error, Result},
+	format::{uri_list, X11ClipboardFormat},
+	server::Server,
+};
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+use x11rb::{
+	connection::Connection,
+	protocol::{
+		xproto::{Atom, AtomEnum, ConnectionExt, Property},
+		Event,
+	},
+	CURRENT_TIME, NONE,
+};
+
+/// How long to wait for the selection owner to answer, or to send the next chunk of an incremental transfer.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A handle to the X11 `CLIPBOARD` selection.
+///
+/// Reading and writing use separate connections, as the contents we've set are served from a background thread,
+/// which keeps running for as long as any clone of this handle is alive.
+#[derive(Clone)]
+pub struct ClipboardHandle(Arc<ClipboardHandleInner>);
+
+struct ClipboardHandleInner {
+	reader: Mutex<Context>,
+	server: Server,
+}
+
+impl ClipboardHandle {
+	pub fn new() -> Result<Self> {
+		Ok(Self(Arc::new(ClipboardHandleInner {
+			reader: Mutex::new(Context::new()?),
+			server: Server::new()?,
+		})))
+	}
+
+	pub fn set_string<StringType: ToString>(&self, string: StringType) -> Result<()> {
+		let string = Arc::<[u8]>::from(string.to_string().into_bytes());
+		self.set_formats(&[
+			(X11ClipboardFormat::Utf8String, string.clone()),
+			(X11ClipboardFormat::PlainTextUtf8, string),
+		])
+	}
+
+	pub fn string(&self) -> Result<Option<String>> {
+		for format in [
+			X11ClipboardFormat::Utf8String,
+			X11ClipboardFormat::PlainTextUtf8,
+		] {
+			if let Some(bytes) = self.get_raw(&format)? {
+				return String::from_utf8(bytes)
+					.map(Some)
+					.map_err(|err| Error::InvalidString(err.utf8_error()));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Gets the list of files on the clipboard, from `text/uri-list`.
+	pub fn files(&self) -> Result<Option<Vec<PathBuf>>> {
+		match self.get_raw(&X11ClipboardFormat::UriList)? {
+			Some(list) => uri_list::parse(&list).map(Some),
+			None => Ok(None),
+		}
+	}
+
+	pub fn set_files<PathType: AsRef<Path>, PathList: AsRef<[PathType]>>(
+		&self,
+		paths: PathList,
+	) -> Result<()> {
+		let list = uri_list::encode(paths.as_ref());
+		self.set_formats(&[(X11ClipboardFormat::UriList, list.into())])
+	}
+
+	/// Gets the image on the clipboard, from `image/png`.
+	pub fn image(&self) -> Result<Option<image::RgbImage>> {
+		match self.get_raw(&X11ClipboardFormat::Png)? {
+			Some(png) => image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+				.map(|image| Some(image.to_rgb8()))
+				.map_err(|_| Error::InvalidImage),
+			None => Ok(None),
+		}
+	}
+
+	/// Asks the selection owner for the clipboard contents, converted to the given format.
+	pub fn get_raw(&self, format: &X11ClipboardFormat) -> Result<Option<Vec<u8>>> {
+		let reader = self.0.reader.lock();
+		let target = reader.atom(format)?;
+		read_selection(&reader, target, format)
+	}
+
+	/// Takes ownership of the clipboard, offering raw bytes as the given format.
+	pub fn set_raw(&self, format: &X11ClipboardFormat, data: &[u8]) -> Result<()> {
+		self.set_formats(&[(format.clone(), data.into())])
+	}
+
+	pub fn empty(&self) -> Result<()> {
+		self.0.server.clear()
+	}
+
+	pub fn available_formats(&self) -> Result<Vec<X11ClipboardFormat>> {
+		let reader = self.0.reader.lock();
+		let targets = reader.atoms.TARGETS;
+		let list = match read_selection(
+			&reader,
+			targets,
+			&X11ClipboardFormat::Other("TARGETS".to_owned()),
+		)? {
+			Some(list) => list,
+			None => return Ok(Vec::new()),
+		};
+		list.chunks_exact(4)
+			.map(|atom| Atom::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+			.filter(|atom| *atom != targets)
+			.map(|atom| reader.format(atom))
+			.collect()
+	}
+
+	fn set_formats(&self, formats: &[(X11ClipboardFormat, Arc<[u8]>)]) -> Result<()> {
+		let reader = self.0.reader.lock();
+		let contents = formats
+			.iter()
+			.map(|(format, data)| Ok((reader.atom(format)?, data.clone())))
+			.collect::<Result<HashMap<_, _>>>()?;
+		self.0.server.set(contents)
+	}
+}
+
+/// Converts the clipboard selection to `target`, and reads the result, following incremental transfers.
+fn read_selection(
+	context: &Context,
+	target: Atom,
+	format: &X11ClipboardFormat,
+) -> Result<Option<Vec<u8>>> {
+	let Context {
+		conn,
+		window,
+		atoms,
+	} = context;
+	conn.convert_selection(
+		*window,
+		atoms.CLIPBOARD,
+		target,
+		atoms.CLIPEE_SELECTION,
+		CURRENT_TIME,
+	)?;
+	conn.flush()?;
+	let mut deadline = Instant::now() + READ_TIMEOUT;
+	// Set once the owner has told us that it's sending the data in chunks.
+	let mut incremental: Option<Vec<u8>> = None;
+	loop {
+		let event = match conn.poll_for_event()? {
+			Some(event) => event,
+			None => {
+				if Instant::now() > deadline {
+					return Err(Error::Timeout {
+						format: format.clone(),
+					});
+				}
+				std::thread::sleep(POLL_INTERVAL);
+				continue;
+			}
+		};
+		match event {
+			Event::SelectionNotify(event) if event.requestor == *window => {
+				if event.property == NONE {
+					return Ok(None);
+				}
+				// Deleting the property is what tells the owner to start an incremental transfer.
+				let reply = conn
+					.get_property(true, *window, event.property, AtomEnum::ANY, 0, u32::MAX)?
+					.reply()?;
+				if reply.type_ != atoms.INCR {
+					return Ok(Some(reply.value));
+				}
+				let size_hint = reply.value32().and_then(|mut size| size.next());
+				incremental = Some(Vec::with_capacity(size_hint.unwrap_or(0) as usize));
+				conn.flush()?;
+				deadline = Instant::now() + READ_TIMEOUT;
+			}
+			Event::PropertyNotify(event)
+				if event.window == *window
+					&& event.atom == atoms.CLIPEE_SELECTION
+					&& event.state == Property::NEW_VALUE =>
+			{
+				let data = match incremental.as_mut() {
+					Some(data) => data,
+					None => continue,
+				};
+				let reply = conn
+					.get_property(true, *window, event.atom, AtomEnum::ANY, 0, u32::MAX)?
+					.reply()?;
+				// A zero-length chunk marks the end of the transfer.
+				if reply.value.is_empty() {
+					return Ok(incremental);
+				}
+				data.extend_from_slice(&reply.value);
+				conn.flush()?;
+				deadline = Instant::now() + READ_TIMEOUT;
+			}
+			_ => {}
+		}
+	}
+}
@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Owns the clipboard selection, and answers requests for its contents from a background thread,
+//! so the contents stay available after the setter returns.
+
+use crate::{
+	context::Context,
+	error::{Error, Result},
+};
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread::JoinHandle,
+};
+use x11rb::{
+	connection::Connection,
+	errors::ConnectionError,
+	protocol::{
+		xproto::{
+			Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt,
+			EventMask, PropMode, Property, PropertyNotifyEvent, SelectionNotifyEvent,
+			SelectionRequestEvent, Window, SELECTION_NOTIFY_EVENT,
+		},
+		Event,
+	},
+	wrapper::ConnectionExt as _,
+	CURRENT_TIME, NONE,
+};
+
+type Contents = HashMap<Atom, Arc<[u8]>>;
+
+pub(crate) struct Server {
+	context: Arc<Context>,
+	contents: Arc<Mutex<Contents>>,
+	running: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+	pub fn new() -> Result<Self> {
+		let context = Arc::new(Context::new()?);
+		let contents = Arc::new(Mutex::new(Contents::new()));
+		let running = Arc::new(AtomicBool::new(true));
+		let thread = std::thread::Builder::new()
+			.name("clipee-x11 selection owner".to_owned())
+			.spawn({
+				let context = context.clone();
+				let contents = contents.clone();
+				let running = running.clone();
+				move || serve(&context, &contents, &running)
+			})
+			.expect("failed to spawn selection owner thread");
+		Ok(Self {
+			context,
+			contents,
+			running,
+			thread: Some(thread),
+		})
+	}
+
+	/// Takes ownership of the clipboard selection, offering the given contents.
+	pub fn set(&self, contents: Contents) -> Result<()> {
+		*self.contents.lock() = contents;
+		let Context {
+			conn,
+			window,
+			atoms,
+		} = &*self.context;
+		conn.set_selection_owner(*window, atoms.CLIPBOARD, CURRENT_TIME)?;
+		if conn.get_selection_owner(atoms.CLIPBOARD)?.reply()?.owner != *window {
+			return Err(Error::SelectionOwnership);
+		}
+		Ok(())
+	}
+
+	/// Drops the clipboard selection, whoever owns it.
+	pub fn clear(&self) -> Result<()> {
+		self.contents.lock().clear();
+		let Context { conn, atoms, .. } = &*self.context;
+		conn.set_selection_owner(NONE, atoms.CLIPBOARD, CURRENT_TIME)?;
+		conn.flush()?;
+		Ok(())
+	}
+}
+
+impl Drop for Server {
+	fn drop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+		// Wake the thread up, so it notices that it should stop.
+		let Context {
+			conn,
+			window,
+			atoms,
+		} = &*self.context;
+		let wake = ClientMessageEvent::new(32, *window, atoms.CLIPEE_SELECTION, [0_u32; 5]);
+		let sent = conn
+			.send_event(false, *window, EventMask::NO_EVENT, wake)
+			.and_then(|_| conn.flush());
+		if sent.is_ok() {
+			if let Some(thread) = self.thread.take() {
+				let _ = thread.join();
+			}
+		}
+	}
+}
+
+/// A transfer of data too big to fit in one property, which is sent in chunks as the requestor deletes each one.
+struct Transfer {
+	requestor: Window,
+	property: Atom,
+	target: Atom,
+	data: Arc<[u8]>,
+	offset: usize,
+}
+
+fn serve(context: &Context, contents: &Mutex<Contents>, running: &AtomicBool) {
+	let mut transfers = Vec::<Transfer>::new();
+	while running.load(Ordering::SeqCst) {
+		let event = match context.conn.wait_for_event() {
+			Ok(event) => event,
+			Err(_) => break,
+		};
+		// A misbehaving requestor shouldn't take down the thread, so errors are ignored.
+		let _ = match event {
+			Event::SelectionRequest(event) => {
+				answer_request(context, contents, &mut transfers, event)
+			}
+			Event::PropertyNotify(event) => continue_transfer(context, &mut transfers, event),
+			Event::SelectionClear(event) if event.selection == context.atoms.CLIPBOARD => {
+				contents.lock().clear();
+				Ok(())
+			}
+			_ => Ok(()),
+		};
+	}
+}
+
+fn answer_request(
+	context: &Context,
+	contents: &Mutex<Contents>,
+	transfers: &mut Vec<Transfer>,
+	event: SelectionRequestEvent,
+) -> std::result::Result<(), ConnectionError> {
+	let Context { conn, atoms, .. } = context;
+	// Obsolete clients leave the property unset, and expect the target to be used as the property.
+	let property = if event.property == NONE {
+		event.target
+	} else {
+		event.property
+	};
+	let contents = contents.lock();
+	let reply_property = if event.selection != atoms.CLIPBOARD {
+		NONE
+	} else if event.target == atoms.TARGETS {
+		let mut targets = vec![atoms.TARGETS];
+		targets.extend(contents.keys());
+		conn.change_property32(
+			PropMode::REPLACE,
+			event.requestor,
+			property,
+			AtomEnum::ATOM,
+			&targets,
+		)?;
+		property
+	} else if let Some(data) = contents.get(&event.target) {
+		if data.len() > context.max_chunk_size() {
+			// We need to know when the requestor deletes the property to send the next chunk.
+			conn.change_window_attributes(
+				event.requestor,
+				&ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+			)?;
+			conn.change_property32(
+				PropMode::REPLACE,
+				event.requestor,
+				property,
+				atoms.INCR,
+				&[u32::try_from(data.len()).unwrap_or(u32::MAX)],
+			)?;
+			transfers.push(Transfer {
+				requestor: event.requestor,
+				property,
+				target: event.target,
+				data: data.clone(),
+				offset: 0,
+			});
+		} else {
+			conn.change_property8(
+				PropMode::REPLACE,
+				event.requestor,
+				property,
+				event.target,
+				data,
+			)?;
+		}
+		property
+	} else {
+		NONE
+	};
+	conn.send_event(
+		false,
+		event.requestor,
+		EventMask::NO_EVENT,
+		SelectionNotifyEvent {
+			response_type: SELECTION_NOTIFY_EVENT,
+			sequence: 0,
+			time: event.time,
+			requestor: event.requestor,
+			selection: event.selection,
+			target: event.target,
+			property: reply_property,
+		},
+	)?;
+	conn.flush()
+}
+
+fn continue_transfer(
+	context: &Context,
+	transfers: &mut Vec<Transfer>,
+	event: PropertyNotifyEvent,
+) -> std::result::Result<(), ConnectionError> {
+	if event.state != Property::DELETE {
+		return Ok(());
+	}
+	let idx = match transfers
+		.iter()
+		.position(|transfer| transfer.requestor == event.window && transfer.property == event.atom)
+	{
+		Some(idx) => idx,
+		None => return Ok(()),
+	};
+	let transfer = &mut transfers[idx];
+	let end = (transfer.offset + context.max_chunk_size()).min(transfer.data.len());
+	let chunk = &transfer.data[transfer.offset..end];
+	context.conn.change_property8(
+		PropMode::REPLACE,
+		transfer.requestor,
+		transfer.property,
+		transfer.target,
+		chunk,
+	)?;
+	// A zero-length chunk marks the end of the transfer.
+	if chunk.is_empty() {
+		transfers.remove(idx);
+	} else {
+		transfer.offset = end;
+	}
+	context.conn.flush()
+}
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use clipee_x11::{format::X11ClipboardFormat, ClipboardHandle};
+use image::{ImageOutputFormat, Rgb, RgbImage};
+use std::io::Cursor;
+
+#[test]
+pub fn image_from_png() {
+	let image = RgbImage::from_fn(3, 2, |x, y| Rgb([x as u8 * 80, y as u8 * 120, 200]));
+	let mut png = Vec::new();
+	image::DynamicImage::ImageRgb8(image.clone())
+		.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+		.expect("failed to encode png");
+	let handle = ClipboardHandle::new().expect("failed to connect to the X server");
+	handle
+		.set_raw(&X11ClipboardFormat::Png, &png)
+		.expect("failed to set png to clipboard");
+	let result = handle
+		.image()
+		.expect("failed to get image from clipboard")
+		.expect("image wasn't set in clipboard");
+	assert_eq!(image, result, "Image didn't survive round-trip!");
+}
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use clipee_x11::ClipboardHandle;
+
+#[test]
+pub fn round_trip_large_string() {
+	// Far bigger than a single request can hold, so this has to go through an INCR transfer.
+	let text = "clipboard ".repeat(1024 * 1024);
+	let handle = ClipboardHandle::new().expect("failed to connect to the X server");
+	handle
+		.set_string(&text)
+		.expect("failed to set string to clipboard");
+	let result = handle
+		.string()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(text.len(), result.len());
+	assert!(text == result, "Large string didn't survive round-trip!");
+}
@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+use clipee_x11::{format::X11ClipboardFormat, ClipboardHandle};
+
+#[test]
+pub fn round_trip_string() {
+	let text = "héllo, selection 🦀";
+	let handle = ClipboardHandle::new().expect("failed to connect to the X server");
+	handle
+		.set_string(text)
+		.expect("failed to set string to clipboard");
+	let result = handle
+		.string()
+		.expect("failed to get string from clipboard")
+		.expect("string wasn't set in clipboard");
+	assert_eq!(text, result, "String didn't survive round-trip!");
+	let formats = handle
+		.available_formats()
+		.expect("failed to get available formats");
+	assert!(formats.contains(&X11ClipboardFormat::Utf8String));
+	assert!(formats.contains(&X11ClipboardFormat::PlainTextUtf8));
+}